@@ -24,13 +24,48 @@ pub struct TokenInfo {
 
 #[contractimpl]
 impl DobLinkContract {
-    // Initialize the contract
-    pub fn init(env: &Env, admin: Address) {
+    // Initialize the contract with its admin, payment asset and token info (once only)
+    pub fn init(env: &Env, admin: Address, payment_token: Address, token_info: TokenInfo) -> Result<(), Error> {
+        if env.storage().instance().has(&symbol_short!("admin")) {
+            return Err(Error::AlreadyInitialized);
+        }
+
         env.storage().instance().set(&symbol_short!("admin"), &admin);
+        env.storage().instance().set(&symbol_short!("payment"), &payment_token);
+        env.storage().instance().set(&symbol_short!("token_info"), &token_info);
+        Ok(())
+    }
+
+    // Get the payment asset investments are made in
+    pub fn get_payment_token(env: &Env) -> Result<Address, Error> {
+        Self::require_initialized(env)?;
+
+        let payment_token: Address = env.storage().instance().get(&symbol_short!("payment")).unwrap();
+        Ok(payment_token)
+    }
+
+    // Halt new investments (admin only)
+    pub fn pause(env: &Env) -> Result<(), Error> {
+        Self::set_paused(env, true)
+    }
+
+    // Resume new investments (admin only)
+    pub fn unpause(env: &Env) -> Result<(), Error> {
+        Self::set_paused(env, false)
+    }
+
+    // Check whether new investments are halted
+    pub fn is_paused(env: &Env) -> bool {
+        env.storage().instance().get(&symbol_short!("paused")).unwrap_or(false)
     }
 
     // Accept USDC investment
     pub fn invest(env: &Env, investor: Address, amount: i128, token_id: Symbol) -> Result<(), Error> {
+        Self::require_initialized(env)?;
+        if Self::is_paused(env) {
+            return Err(Error::Paused);
+        }
+
         // Validate amount
         if amount <= 0 {
             return Err(Error::InvalidAmount);
@@ -57,6 +92,8 @@ impl DobLinkContract {
 
     // Get investment by investor
     pub fn get_investment(env: &Env, investor: Address) -> Result<Vec<Investment>, Error> {
+        Self::require_initialized(env)?;
+
         let investments: Vec<Investment> = env.storage().instance().get(&symbol_short!("investments")).unwrap_or(Vec::new(&env));
         
         let mut user_investments = Vec::new(&env);
@@ -71,12 +108,16 @@ impl DobLinkContract {
 
     // Get all investments
     pub fn get_all_investments(env: &Env) -> Result<Vec<Investment>, Error> {
+        Self::require_initialized(env)?;
+
         let investments: Vec<Investment> = env.storage().instance().get(&symbol_short!("investments")).unwrap_or(Vec::new(&env));
         Ok(investments)
     }
 
     // Register token info
     pub fn register_token(env: &Env, token_info: TokenInfo) -> Result<(), Error> {
+        Self::require_initialized(env)?;
+
         // Check if caller is admin
         let admin: Address = env.storage().instance().get(&symbol_short!("admin")).unwrap();
         let caller = env.current_contract_address();
@@ -91,14 +132,31 @@ impl DobLinkContract {
 
     // Get token info
     pub fn get_token_info(env: &Env) -> Result<TokenInfo, Error> {
-        let token_info: TokenInfo = env.storage().instance().get(&symbol_short!("token_info")).unwrap_or(TokenInfo {
-            symbol: symbol_short!("DOB"),
-            name: symbol_short!("DOB Token"),
-            total_supply: 0,
-            decimals: 7,
-        });
+        Self::require_initialized(env)?;
+
+        let token_info: TokenInfo = env.storage().instance().get(&symbol_short!("token_info")).ok_or(Error::TokenNotFound)?;
         Ok(token_info)
     }
+
+    // Helper function to set the pause flag with the admin's authorization
+    fn set_paused(env: &Env, paused: bool) -> Result<(), Error> {
+        Self::require_initialized(env)?;
+
+        let admin: Address = env.storage().instance().get(&symbol_short!("admin")).unwrap();
+        admin.require_auth();
+
+        env.storage().instance().set(&symbol_short!("paused"), &paused);
+        env.events().publish((symbol_short!("paused"),), paused);
+        Ok(())
+    }
+
+    // Helper function to reject calls made before init
+    fn require_initialized(env: &Env) -> Result<(), Error> {
+        if !env.storage().instance().has(&symbol_short!("admin")) {
+            return Err(Error::NotInitialized);
+        }
+        Ok(())
+    }
 }
 
 #[contracttype]
@@ -107,6 +165,9 @@ pub enum Error {
     InvalidAmount,
     NotAuthorized,
     TokenNotFound,
+    AlreadyInitialized,
+    NotInitialized,
+    Paused,
 }
 
 impl From<Error> for soroban_sdk::Error {
@@ -115,6 +176,9 @@ impl From<Error> for soroban_sdk::Error {
             Error::InvalidAmount => soroban_sdk::Error::from_type_and_code(1, 1),
             Error::NotAuthorized => soroban_sdk::Error::from_type_and_code(1, 2),
             Error::TokenNotFound => soroban_sdk::Error::from_type_and_code(1, 3),
+            Error::AlreadyInitialized => soroban_sdk::Error::from_type_and_code(1, 4),
+            Error::NotInitialized => soroban_sdk::Error::from_type_and_code(1, 5),
+            Error::Paused => soroban_sdk::Error::from_type_and_code(1, 6),
         }
     }
 }
@@ -124,6 +188,15 @@ mod test {
     use super::*;
     use soroban_sdk::testutils::{Address as _, Ledger};
 
+    fn token_info() -> TokenInfo {
+        TokenInfo {
+            symbol: symbol_short!("DOB"),
+            name: symbol_short!("DOB Token"),
+            total_supply: 0,
+            decimals: 7,
+        }
+    }
+
     #[test]
     fn test_init() {
         let env = Env::default();
@@ -132,7 +205,7 @@ mod test {
         let contract_id = env.register_contract(None, DobLinkContract);
         let client = DobLinkContractClient::new(&env, &contract_id);
         
-        client.init(&admin);
+        client.init(&admin, &Address::generate(&env), &token_info());
         
         let stored_admin: Address = env.storage().instance().get(&symbol_short!("admin")).unwrap();
         assert_eq!(stored_admin, admin);
@@ -147,7 +220,7 @@ mod test {
         let contract_id = env.register_contract(None, DobLinkContract);
         let client = DobLinkContractClient::new(&env, &contract_id);
         
-        client.init(&admin);
+        client.init(&admin, &Address::generate(&env), &token_info());
         
         // Test investment
         let result = client.invest(&investor, &1000, &symbol_short!("USDC"));
@@ -168,10 +241,67 @@ mod test {
         let contract_id = env.register_contract(None, DobLinkContract);
         let client = DobLinkContractClient::new(&env, &contract_id);
         
-        client.init(&admin);
+        client.init(&admin, &Address::generate(&env), &token_info());
         
         // Test invalid amount
         let result = client.invest(&investor, &0, &symbol_short!("USDC"));
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_init_only_once() {
+        let env = Env::default();
+        let admin = Address::generate(&env);
+        let payment_token = Address::generate(&env);
+        
+        let contract_id = env.register_contract(None, DobLinkContract);
+        let client = DobLinkContractClient::new(&env, &contract_id);
+        
+        client.init(&admin, &payment_token, &token_info());
+        
+        // A second init cannot replace the admin or the token
+        let result = client.try_init(&Address::generate(&env), &Address::generate(&env), &token_info());
+        assert!(result.is_err());
+        assert_eq!(client.get_payment_token(), payment_token);
+    }
+
+    #[test]
+    fn test_calls_before_init() {
+        let env = Env::default();
+        let investor = Address::generate(&env);
+        
+        let contract_id = env.register_contract(None, DobLinkContract);
+        let client = DobLinkContractClient::new(&env, &contract_id);
+        
+        assert!(client.try_invest(&investor, &1000, &symbol_short!("USDC")).is_err());
+        assert!(client.try_get_investment(&investor).is_err());
+        assert!(client.try_get_all_investments().is_err());
+        assert!(client.try_register_token(&token_info()).is_err());
+        assert!(client.try_get_token_info().is_err());
+        assert!(client.try_get_payment_token().is_err());
+    }
+
+    #[test]
+    fn test_pause() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let admin = Address::generate(&env);
+        let investor = Address::generate(&env);
+        
+        let contract_id = env.register_contract(None, DobLinkContract);
+        let client = DobLinkContractClient::new(&env, &contract_id);
+        
+        client.init(&admin, &Address::generate(&env), &token_info());
+        
+        client.pause();
+        assert!(client.is_paused());
+        assert!(client.try_invest(&investor, &1000, &symbol_short!("USDC")).is_err());
+        
+        // Views keep working while paused
+        assert_eq!(client.get_all_investments().unwrap().len(), 0);
+        
+        client.unpause();
+        assert!(!client.is_paused());
+        assert!(client.try_invest(&investor, &1000, &symbol_short!("USDC")).is_ok());
+    }
 }
@@ -6,6 +6,15 @@ use soroban_sdk::{
     Address, Env, Symbol,
 };
 
+fn token_info() -> TokenInfo {
+    TokenInfo {
+        symbol: symbol_short!("DOB"),
+        name: symbol_short!("DOB Token"),
+        total_supply: 0,
+        decimals: 7,
+    }
+}
+
 #[test]
 fn test_contract_initialization() {
     let env = Env::default();
@@ -14,7 +23,7 @@ fn test_contract_initialization() {
     let contract_id = env.register_contract(None, DobLinkContract);
     let client = DobLinkContractClient::new(&env, &contract_id);
     
-    client.init(&admin);
+    client.init(&admin, &Address::generate(&env), &token_info());
     
     // Verify admin is set correctly
     let stored_admin: Address = env.storage().instance().get(&symbol_short!("admin")).unwrap();
@@ -30,7 +39,7 @@ fn test_investment_functionality() {
     let contract_id = env.register_contract(None, DobLinkContract);
     let client = DobLinkContractClient::new(&env, &contract_id);
     
-    client.init(&admin);
+    client.init(&admin, &Address::generate(&env), &token_info());
     
     // Test successful investment
     let result = client.invest(&investor, &1000, &symbol_short!("USDC"));
@@ -56,7 +65,7 @@ fn test_multiple_investments() {
     let contract_id = env.register_contract(None, DobLinkContract);
     let client = DobLinkContractClient::new(&env, &contract_id);
     
-    client.init(&admin);
+    client.init(&admin, &Address::generate(&env), &token_info());
     
     // Multiple investments from different investors
     client.invest(&investor1, &500, &symbol_short!("USDC")).unwrap();
@@ -83,7 +92,7 @@ fn test_invalid_investment_amount() {
     let contract_id = env.register_contract(None, DobLinkContract);
     let client = DobLinkContractClient::new(&env, &contract_id);
     
-    client.init(&admin);
+    client.init(&admin, &Address::generate(&env), &token_info());
     
     // Test zero amount
     let result = client.invest(&investor, &0, &symbol_short!("USDC"));
@@ -102,7 +111,7 @@ fn test_token_registration() {
     let contract_id = env.register_contract(None, DobLinkContract);
     let client = DobLinkContractClient::new(&env, &contract_id);
     
-    client.init(&admin);
+    client.init(&admin, &Address::generate(&env), &token_info());
     
     // Create token info
     let token_info = TokenInfo {
@@ -125,16 +134,16 @@ fn test_token_registration() {
 }
 
 #[test]
-fn test_default_token_info() {
+fn test_init_token_info() {
     let env = Env::default();
     let admin = Address::generate(&env);
     
     let contract_id = env.register_contract(None, DobLinkContract);
     let client = DobLinkContractClient::new(&env, &contract_id);
     
-    client.init(&admin);
+    client.init(&admin, &Address::generate(&env), &token_info());
     
-    // Get default token info when none is set
+    // Get the token info passed to init
     let token_info = client.get_token_info().unwrap();
     assert_eq!(token_info.symbol, symbol_short!("DOB"));
     assert_eq!(token_info.name, symbol_short!("DOB Token"));
@@ -151,7 +160,7 @@ fn test_empty_investments() {
     let contract_id = env.register_contract(None, DobLinkContract);
     let client = DobLinkContractClient::new(&env, &contract_id);
     
-    client.init(&admin);
+    client.init(&admin, &Address::generate(&env), &token_info());
     
     // Test getting investments for investor with no investments
     let investments = client.get_investment(&investor).unwrap();
@@ -171,7 +180,7 @@ fn test_timestamp_tracking() {
     let contract_id = env.register_contract(None, DobLinkContract);
     let client = DobLinkContractClient::new(&env, &contract_id);
     
-    client.init(&admin);
+    client.init(&admin, &Address::generate(&env), &token_info());
     
     // Set a specific timestamp for testing
     env.ledger().set_timestamp(1234567890);
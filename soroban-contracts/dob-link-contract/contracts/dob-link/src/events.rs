@@ -0,0 +1,202 @@
+use soroban_sdk::{contracttype, Address, BytesN, String};
+
+use crate::{FeeConfig, InvestmentStatus, KycRecord, Role, Settlement};
+
+// Version of the event payloads below; bump it whenever a field changes meaning or shape.
+// Topics always start with the event name, followed by token_id and buyer where an event has them.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+// ("ADMPROP",)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdminProposed {
+    pub schema_version: u32,
+    pub current_admin: Address,
+    pub pending_admin: Address,
+}
+
+// ("ADMIN",)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdminChanged {
+    pub schema_version: u32,
+    pub previous_admin: Address,
+    pub new_admin: Address,
+}
+
+// ("UPGRADED",)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Upgraded {
+    pub schema_version: u32,
+    pub wasm_hash: BytesN<32>,
+}
+
+// ("MIGRATED",)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Migrated {
+    pub schema_version: u32,
+    pub from_storage_version: u32,
+    pub to_storage_version: u32,
+}
+
+// ("FEESCHED",)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeScheduled {
+    pub schema_version: u32,
+    pub config: FeeConfig,
+    pub effective_at: u64,
+}
+
+// ("FEEWDRAW",)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeesWithdrawn {
+    pub schema_version: u32,
+    pub operator: Address,
+    pub to: Address,
+    pub amount: i128,
+}
+
+// ("ROLE", account)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RoleChanged {
+    pub schema_version: u32,
+    pub role: Role,
+    pub account: Address,
+    pub token_id: Option<String>, // None for a grant covering every token
+    pub granted: bool, // false when revoked
+}
+
+// ("KYC", investor)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KycUpdated {
+    pub schema_version: u32,
+    pub investor: Address,
+    pub record: KycRecord,
+}
+
+// ("PAUSED", token_id), with no token_id for a contract-wide pause
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Paused {
+    pub schema_version: u32,
+    pub operator: Address,
+    pub token_id: Option<String>,
+    pub paused: bool, // false when lifted
+}
+
+// ("TOKENREG", token_id)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenRegistered {
+    pub schema_version: u32,
+    pub token_id: String,
+    pub name: String,
+    pub share_token: Address,
+}
+
+// ("TOKENUPD", token_id)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenUpdated {
+    pub schema_version: u32,
+    pub token_id: String,
+    pub operator: Address,
+}
+
+// ("TOKENDEL", token_id)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenDelisted {
+    pub schema_version: u32,
+    pub token_id: String,
+}
+
+// ("WIDGETREG", token_id)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WidgetRegistered {
+    pub schema_version: u32,
+    pub widget_hash: String,
+    pub token_id: String,
+    pub owner: Address,
+    pub payout: Address,
+}
+
+// ("WIDGETOFF", token_id)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WidgetDeactivated {
+    pub schema_version: u32,
+    pub widget_hash: String,
+    pub token_id: String,
+    pub owner: Address,
+}
+
+// ("REFCLAIM", payout)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReferralClaimed {
+    pub schema_version: u32,
+    pub payout: Address,
+    pub amount: i128,
+}
+
+// ("INVESTED", token_id, buyer)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvestmentCreated {
+    pub schema_version: u32,
+    pub investment_id: u32,
+    pub token_id: String,
+    pub buyer: Address,
+    pub amount: i128,
+    pub widget_hash: Option<String>,
+}
+
+// ("INVSTAT", token_id, buyer)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StatusChanged {
+    pub schema_version: u32,
+    pub investment_id: u32,
+    pub token_id: String,
+    pub buyer: Address,
+    pub old_status: InvestmentStatus,
+    pub new_status: InvestmentStatus,
+    pub settlement: Settlement, // How the escrow was split between buyer, issuer and fees
+}
+
+// ("FINALIZED", token_id)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OfferingFinalized {
+    pub schema_version: u32,
+    pub token_id: String,
+    pub soft_cap_met: bool,
+}
+
+// ("YIELDDEP", token_id)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct YieldDeposited {
+    pub schema_version: u32,
+    pub token_id: String,
+    pub operator: Address,
+    pub amount: i128,
+}
+
+// ("YIELDCLM", token_id, buyer)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct YieldClaimed {
+    pub schema_version: u32,
+    pub token_id: String,
+    pub buyer: Address,
+    pub amount: i128,
+}
@@ -1,15 +1,212 @@
 #![no_std]
 use soroban_sdk::{
-    contract, contractimpl, contracttype, symbol_short, vec, Address, Env, Map, String, Vec, Error,
+    contract, contractclient, contracterror, contractimpl, contracttype, symbol_short, token, vec,
+    Address, BytesN, Env, Map, String, Vec,
 };
-use soroban_sdk::xdr::{ScErrorType, ScErrorCode};
+
+mod events;
+pub use events::*;
+
+// Layout of the data in storage; bump together with a migrate() step when it changes.
+// Version 1 kept every investment in a single instance `INV` map and its one offering in a `TOKEN` slot.
+const STORAGE_VERSION: u32 = 2;
+
+// Ledger TTL bounds (~5s per ledger)
+const DAY_IN_LEDGERS: u32 = 17280;
+const INSTANCE_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+const INSTANCE_LIFETIME_THRESHOLD: u32 = INSTANCE_BUMP_AMOUNT - DAY_IN_LEDGERS;
+const INVESTMENT_BUMP_AMOUNT: u32 = 180 * DAY_IN_LEDGERS;
+const INVESTMENT_LIFETIME_THRESHOLD: u32 = INVESTMENT_BUMP_AMOUNT - 7 * DAY_IN_LEDGERS;
+
+// Upper bound on ids returned by one paginated call
+const MAX_PAGE_SIZE: u32 = 50;
+
+// Pending investments settled by one finalize_offering call. A completion writes up to 4 ledger
+// entries (investment, share balance, yield position, referral balance) and reads 2 more, on top
+// of about 7 shared ones, which keeps a batch within the 25 write / 40 read entry transaction limits.
+const FINALIZE_BATCH_SIZE: u32 = 4;
+
+// Version 1 investments moved by one migrate call. A completed one writes 6 ledger entries
+// (investment, buyer index, token index, investor total, share balance, yield position) on top
+// of 3 shared ones, so a batch of 2 writes 15, keeping within the same limits as finalization.
+const MIGRATE_BATCH_SIZE: u32 = 2;
+
+// Fixed-point scale of the per-token yield accumulator, per whole share; the accumulator
+// itself is scaled by this times 10^decimals of the token's share token
+const YIELD_PRECISION: i128 = 1_000;
+
+// Upper bound on the platform fee, in basis points (5%)
+const MAX_PLATFORM_FEE_BPS: u32 = 500;
+
+// Upper bound on a token's referral fee, in basis points (5%)
+const MAX_REFERRAL_FEE_BPS: u32 = 500;
+
+// Delay between scheduling a fee change and it applying to completions
+const FEE_TIMELOCK: u64 = 7 * 24 * 60 * 60;
+
+// How long a pending investment waits on the admin before its buyer can claim a refund
+const REFUND_DELAY: u64 = 30 * 24 * 60 * 60;
+
+// Upper bound on share token decimals, keeping amount * 10^decimals and the yield accumulator scale well inside i128
+const MAX_SHARE_DECIMALS: u32 = 18;
+
+// Upper bound on a token's cancel window; past the refund delay the buyer can claim a refund instead
+const MAX_CANCEL_WINDOW: u64 = REFUND_DELAY;
 
 #[contract]
 pub struct DobLinkContract;
 
+// Share token calls DobLinkContract makes as the mint authority (see contracts/share-token)
+#[contractclient(name = "ShareTokenClient")]
+pub trait ShareTokenInterface {
+    fn admin(env: Env) -> Address;
+    fn set_transfer_hook(env: Env, hook: Address);
+    fn mint(env: Env, to: Address, amount: i128);
+    fn decimals(env: Env) -> u32;
+    fn total_supply(env: Env) -> i128;
+}
+
+// Eligibility check an issuer's own compliance registry exposes (see contracts/mock-compliance)
+#[contractclient(name = "ComplianceClient")]
+pub trait ComplianceInterface {
+    fn is_eligible(env: Env, buyer: Address, token_id: String, amount: i128) -> bool;
+}
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum ContractError {
+    AlreadyInitialized = 1,
+    NotInitialized = 2,
+    Unauthorized = 3,
+    BelowMinimum = 4,
+    AboveMaximum = 5,
+    TokenNotFound = 6,
+    TokenAlreadyExists = 7,
+    InvalidTokenConfig = 8,
+    InvestmentNotFound = 9,
+    InvalidStatus = 10,
+    Paused = 11,
+    NoPendingAdmin = 12,
+    InvalidPageSize = 13,
+    RefundNotAvailable = 14,
+    CancellationWindowClosed = 15,
+    RaiseCapExceeded = 16,
+    SaleNotOpen = 17,
+    SaleNotEnded = 18,
+    InvestorLimitExceeded = 19,
+    NotVerified = 20,
+    KycExpired = 21,
+    JurisdictionBlocked = 22,
+    NotEligible = 23,
+    NoYieldHolders = 24,
+    WidgetAlreadyExists = 25,
+    WidgetNotFound = 26,
+    WidgetInactive = 27,
+    WidgetTokenMismatch = 28,
+    InvalidFeeConfig = 29,
+    InsufficientFees = 30,
+    EscrowOutstanding = 31,
+    InvalidShareToken = 32,
+    LegacyConfigRequired = 33,
+    SoftCapNotMet = 34,
+}
+
+// Delegated permissions; the admin holds all of them implicitly
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Role {
+    TokenManager, // Updates TokenInfo
+    StatusUpdater, // Moves investments between statuses
+    Compliance, // Manages investor verification and offerings' KYC and compliance settings
+    Treasurer, // Moves contract-held funds and sets offerings' treasuries
+    Pauser, // Pauses and unpauses trading
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KycStatus {
+    Verified,
+    Revoked,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KycRecord {
+    pub status: KycStatus,
+    pub expires_at: u64, // Ledger timestamp the verification lapses at
+    pub jurisdiction: String, // ISO 3166-1 alpha-2 country code
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InvestmentStatus {
+    Pending,
+    Completed,
+    Failed,
+    Refunded,
+    Cancelled,
+}
+
+impl InvestmentStatus {
+    // Only pending investments can move, and every other state is final
+    pub fn can_transition_to(&self, next: InvestmentStatus) -> bool {
+        matches!(self, InvestmentStatus::Pending) && next != InvestmentStatus::Pending
+    }
+}
+
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Investment {
+    pub buyer: Address,
+    pub token_id: String,
+    pub amount: i128,
+    pub timestamp: u64,
+    pub status: InvestmentStatus,
+    pub shares: i128, // Share tokens minted to the buyer once completed
+    pub widget_hash: Option<String>, // Widget the investment came through, if any
+}
+
+// How an investment's escrow was paid out when it left Pending
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Settlement {
+    pub refunded: i128, // Returned to the buyer; the whole amount unless completed
+    pub proceeds: i128, // Sent to the issuer treasury
+    pub referral_fee: i128, // Accrued to the widget's payout address
+    pub referral_payout: Option<Address>,
+    pub platform_fee: i128, // Accrued to the platform
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeConfig {
+    pub fee_bps: u32, // Platform fee charged on completion, at most MAX_PLATFORM_FEE_BPS
+    pub treasury: Address, // The only destination accrued fees can be withdrawn to
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScheduledFeeConfig {
+    pub config: FeeConfig,
+    pub effective_at: u64, // Ledger timestamp the config starts applying at
+}
+
+// An embeddable widget, keyed by the hash the backend issued for it
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Widget {
+    pub owner: Address,
+    pub payout: Address, // Receives referral fees earned through the widget
+    pub token_id: String, // The only token investments through this widget may target
+    pub active: bool,
+    pub created_at: u64,
+}
+
+// Investment record as stored by storage version 1
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacyInvestment {
     pub buyer: Address,
     pub token_id: String,
     pub amount: i128,
@@ -17,215 +214,1546 @@ pub struct Investment {
     pub status: String, // "pending", "completed", "failed"
 }
 
+// Token offering as stored by storage version 1, in its single `TOKEN` slot
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacyTokenInfo {
+    pub id: String,
+    pub name: String,
+    pub apy: i128,
+    pub total_value_locked: i128,
+    pub min_investment: i128,
+    pub max_investment: i128,
+}
+
+// What storage version 1 never recorded, supplied by the admin when migrating from it.
+// The offering's id, name, apy, limits and target raise (its old total_value_locked) are
+// carried over from the `TOKEN` slot; every other field is taken from token.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacyConfig {
+    pub payment_token: Address,
+    pub token: TokenInfo,
+}
+
+// A holder's share of a token's yield, in the style of a reward-per-share accumulator.
+// Yield follows the shares rather than the investment that minted them: the share token
+// reports every transfer and burn through on_share_transfer, so whoever holds the shares
+// when yield is deposited earns it, and yield accrued before a transfer stays with the sender.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct YieldPosition {
+    pub balance: i128, // Shares held, each earning an equal part of the yield
+    pub reward_debt: i128, // balance * accumulator at the last settlement
+    pub owed: i128, // Settled yield not yet claimed
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvestmentPage {
+    pub ids: Vec<u32>,
+    pub next_cursor: Option<u32>, // None once the last page has been returned
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Finalization {
+    pub soft_cap_met: bool, // Decided once, when finalization starts
+    pub cursor: u32, // Position in the token's investment index to resume from
+    pub done: bool,
+}
+
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct TokenInfo {
     pub id: String,
     pub name: String,
     pub apy: i128, // APY in basis points (e.g., 1250 = 12.5%)
-    pub total_value_locked: i128,
+    pub target_raise: i128, // Hard cap on the amount raised
+    pub allow_partial_fill: bool, // Accept the remainder of an investment that would exceed the cap, if it meets min_investment
     pub min_investment: i128,
     pub max_investment: i128,
+    pub max_per_investor: i128, // Cap on a buyer's open total across all their investments
+    pub treasury: Address, // Receives the proceeds of completed investments
+    pub cancel_window: u64, // Seconds a buyer can cancel a pending investment (0 disables)
+    pub share_token: Address, // SEP-41 share token this contract mints on completion
+    pub share_price: i128, // Payment units per whole share
+    pub sale_start: u64, // Ledger timestamp the offering opens at
+    pub sale_end: u64, // Ledger timestamp the offering closes at
+    pub soft_cap: i128, // Minimum raise for the offering to succeed at finalization
+    pub require_kyc: bool, // Only accept buyers with a current KYC record
+    pub blocked_jurisdictions: Vec<String>, // Jurisdiction codes refused even when verified
+    pub compliance_contract: Option<Address>, // External registry consulted instead of the built-in KYC allowlist
+    pub referral_fee_bps: u32, // Share of a widget-sourced investment paid to the widget on completion
+}
+
+// A token offering as returned to callers, with the figures the contract maintains for it
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenView {
+    pub token: TokenInfo,
+    pub raised: i128, // Live amount raised
+    pub fill_bps: u32, // raised / target_raise in basis points
 }
 
 #[contractimpl]
 impl DobLinkContract {
-    // Initialize the contract with default token info
-    pub fn init(env: Env, admin: Address) -> Result<(), Error> {
+    // Initialize the contract with its admin, payment asset and first token offering
+    pub fn init(env: Env, admin: Address, payment_token: Address, token: TokenInfo) -> Result<(), ContractError> {
+        let init_key = symbol_short!("INIT");
+        if env.storage().instance().has(&init_key) {
+            return Err(ContractError::AlreadyInitialized);
+        }
+        Self::validate_token(&token)?;
+        Self::validate_share_token(&env, &token.share_token)?;
+        Self::attach_share_token(&env, &token)?;
+
         let admin_key = symbol_short!("ADMIN");
         env.storage().instance().set(&admin_key, &admin);
-        
-        // Initialize default token info
-        let default_token = TokenInfo {
-            id: String::from_str(&env, "EVCHARGER001"),
-            name: String::from_str(&env, "Electric Vehicle Charging Network"),
-            apy: 1250, // 12.5%
-            total_value_locked: 2400000000, // $2.4M in micro units
-            min_investment: 10000000, // $10 in micro units
-            max_investment: 100000000000, // $100K in micro units
-        };
-        
-        let token_key = symbol_short!("TOKEN");
-        env.storage().instance().set(&token_key, &default_token);
+
+        let payment_key = symbol_short!("PAYMENT");
+        env.storage().instance().set(&payment_key, &payment_token);
+
+        Self::write_token(&env, &token);
+        env.storage().instance().set(&symbol_short!("VERSION"), &STORAGE_VERSION);
+        env.storage().instance().set(&init_key, &true);
+        Self::extend_instance(&env);
         
         Ok(())
     }
 
     // Get the admin address
-    pub fn get_admin(env: Env) -> Result<Address, Error> {
+    pub fn get_admin(env: Env) -> Result<Address, ContractError> {
+        Self::require_initialized(&env)?;
+
         let admin_key = symbol_short!("ADMIN");
-        env.storage().instance().get(&admin_key).ok_or(Error::from_type_and_code(ScErrorType::Auth, ScErrorCode::MissingValue))
+        env.storage().instance().get(&admin_key).ok_or(ContractError::NotInitialized)
     }
 
-    // Update token information (admin only)
-    pub fn update_token_info(
-        env: Env,
-        token_id: String,
-        name: String,
-        apy: i128,
-        total_value_locked: i128,
-        min_investment: i128,
-        max_investment: i128,
-    ) -> Result<(), Error> {
-        let admin = Self::get_admin(env.clone())?;
-        if env.current_contract_address() != admin {
-            return Err(Error::from_type_and_code(ScErrorType::Auth, ScErrorCode::InvalidAction));
-        }
+    // Start handing the admin role over to a new address (admin only)
+    pub fn transfer_admin(env: Env, new_admin: Address) -> Result<(), ContractError> {
+        let current_admin = Self::require_admin(&env)?;
 
-        let token_info = TokenInfo {
-            id: token_id,
-            name,
-            apy,
-            total_value_locked,
-            min_investment,
-            max_investment,
-        };
+        let pending_key = symbol_short!("PENDADM");
+        env.storage().instance().set(&pending_key, &new_admin);
+
+        env.events().publish(
+            (symbol_short!("ADMPROP"),),
+            AdminProposed { schema_version: EVENT_SCHEMA_VERSION, current_admin, pending_admin: new_admin },
+        );
 
-        let token_key = symbol_short!("TOKEN");
-        env.storage().instance().set(&token_key, &token_info);
-        
         Ok(())
     }
 
-    // Get current token information
-    pub fn get_token_info(env: Env) -> Result<TokenInfo, Error> {
-        let token_key = symbol_short!("TOKEN");
-        env.storage().instance().get(&token_key).ok_or(Error::from_type_and_code(ScErrorType::Storage, ScErrorCode::MissingValue))
+    // Complete the admin handover (pending admin only)
+    pub fn accept_admin(env: Env) -> Result<(), ContractError> {
+        Self::require_initialized(&env)?;
+
+        let pending_key = symbol_short!("PENDADM");
+        let new_admin: Address = env.storage().instance().get(&pending_key).ok_or(ContractError::NoPendingAdmin)?;
+        new_admin.require_auth();
+
+        let previous_admin = Self::get_admin(env.clone())?;
+        let admin_key = symbol_short!("ADMIN");
+        env.storage().instance().set(&admin_key, &new_admin);
+        env.storage().instance().remove(&pending_key);
+
+        env.events().publish((symbol_short!("ADMIN"),), AdminChanged { schema_version: EVENT_SCHEMA_VERSION, previous_admin, new_admin });
+
+        Ok(())
     }
 
-    // Create a new investment
-    pub fn create_investment(
-        env: Env,
-        buyer: Address,
-        token_id: String,
-        amount: i128,
-    ) -> Result<u32, Error> {
-        // Validate amount
-        let token_info = Self::get_token_info(env.clone())?;
-        if amount < token_info.min_investment {
-            return Err(Error::from_type_and_code(ScErrorType::Value, ScErrorCode::InvalidInput));
+    // Get the address the admin role is being handed over to, if any
+    pub fn get_pending_admin(env: Env) -> Result<Option<Address>, ContractError> {
+        Self::require_initialized(&env)?;
+
+        let pending_key = symbol_short!("PENDADM");
+        Ok(env.storage().instance().get(&pending_key))
+    }
+
+    // Get the payment token (USDC Stellar Asset Contract) address
+    pub fn get_payment_token(env: Env) -> Result<Address, ContractError> {
+        Self::require_initialized(&env)?;
+
+        let payment_key = symbol_short!("PAYMENT");
+        env.storage().instance().get(&payment_key).ok_or(ContractError::NotInitialized)
+    }
+
+    // Replace the contract code in place, keeping its address and storage (admin only)
+    pub fn upgrade(env: Env, new_wasm_hash: BytesN<32>) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+
+        env.deployer().update_current_contract_wasm(new_wasm_hash.clone());
+
+        env.events().publish((symbol_short!("UPGRADED"),), Upgraded { schema_version: EVENT_SCHEMA_VERSION, wasm_hash: new_wasm_hash });
+
+        Ok(())
+    }
+
+    // Bring storage written by an older version up to the current layout (admin only).
+    // Version 1 investments move over in batches, so call until it returns the current
+    // version. The first call on version 1 storage needs legacy_config; it is ignored
+    // otherwise. Safe to call repeatedly; it does nothing once storage is current.
+    pub fn migrate(env: Env, legacy_config: Option<LegacyConfig>) -> Result<u32, ContractError> {
+        // Version 1 never wrote INIT, so the admin is read directly
+        let admin: Address = env.storage().instance().get(&symbol_short!("ADMIN")).ok_or(ContractError::NotInitialized)?;
+        admin.require_auth();
+
+        let version = Self::get_storage_version(env.clone())?;
+        if version >= STORAGE_VERSION {
+            return Ok(version);
         }
-        if amount > token_info.max_investment {
-            return Err(Error::from_type_and_code(ScErrorType::Value, ScErrorCode::InvalidInput));
+
+        if version < 2 {
+            // The first call converts the token slot; later ones resume the investment map
+            let progress_key = symbol_short!("MIGRATE");
+            let (token_id, cursor): (String, u32) = match env.storage().instance().get(&progress_key) {
+                Some(progress) => progress,
+                None => {
+                    let legacy_config = legacy_config.ok_or(ContractError::LegacyConfigRequired)?;
+                    (Self::migrate_token_slot(&env, legacy_config)?.id, 0)
+                }
+            };
+            let token_info = Self::read_token(&env, &token_id)?;
+            match Self::migrate_investment_map(&env, &token_info, cursor) {
+                Some(cursor) => {
+                    env.storage().instance().set(&progress_key, &(token_id, cursor));
+                    Self::extend_instance(&env);
+                    return Ok(version);
+                }
+                None => env.storage().instance().remove(&progress_key),
+            }
         }
 
-        // Create investment record
-        let investment = Investment {
-            buyer: buyer.clone(),
-            token_id: token_id.clone(),
-            amount,
-            timestamp: env.ledger().timestamp(),
-            status: String::from_str(&env, "pending"),
-        };
+        env.storage().instance().set(&symbol_short!("VERSION"), &STORAGE_VERSION);
+        env.storage().instance().set(&symbol_short!("INIT"), &true);
+        Self::extend_instance(&env);
 
-        // Store investment
-        let investment_id = Self::get_next_investment_id(env.clone())?;
-        let investment_key = symbol_short!("INV");
-        let mut investments: Map<u32, Investment> = env.storage().instance().get(&investment_key).unwrap_or(Map::new(&env));
-        investments.set(investment_id, investment);
-        env.storage().instance().set(&investment_key, &investments);
+        env.events().publish(
+            (symbol_short!("MIGRATED"),),
+            Migrated { schema_version: EVENT_SCHEMA_VERSION, from_storage_version: version, to_storage_version: STORAGE_VERSION },
+        );
 
-        // Update investment counter
-        let counter_key = symbol_short!("CNT");
-        let next_id = investment_id + 1;
-        env.storage().instance().set(&counter_key, &next_id);
+        Ok(STORAGE_VERSION)
+    }
+
+    // Get the storage layout version, 1 for data written before versioning existed
+    pub fn get_storage_version(env: Env) -> Result<u32, ContractError> {
+        if !env.storage().instance().has(&symbol_short!("ADMIN")) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        Ok(env.storage().instance().get(&symbol_short!("VERSION")).unwrap_or(1))
+    }
+
+    // Schedule a new platform fee and treasury, applying after FEE_TIMELOCK (admin only)
+    pub fn set_fee_config(env: Env, fee_bps: u32, treasury: Address) -> Result<u64, ContractError> {
+        Self::require_admin(&env)?;
+
+        if fee_bps > MAX_PLATFORM_FEE_BPS {
+            return Err(ContractError::InvalidFeeConfig);
+        }
+
+        // Settle a scheduled change that has already taken effect before replacing it
+        if let Some(current) = Self::get_fee_config(env.clone())? {
+            env.storage().instance().set(&symbol_short!("FEECFG"), &current);
+        }
+
+        let scheduled = ScheduledFeeConfig {
+            config: FeeConfig { fee_bps, treasury },
+            effective_at: env.ledger().timestamp() + FEE_TIMELOCK,
+        };
+        env.storage().instance().set(&symbol_short!("FEENEXT"), &scheduled);
+        Self::extend_instance(&env);
 
-        // Emit event
         env.events().publish(
-            (symbol_short!("INVESTED"),),
-            (investment_id, buyer, token_id, amount),
+            (symbol_short!("FEESCHED"),),
+            FeeScheduled { schema_version: EVENT_SCHEMA_VERSION, config: scheduled.config, effective_at: scheduled.effective_at },
         );
 
-        Ok(investment_id)
+        Ok(scheduled.effective_at)
     }
 
-    // Get investment by ID
-    pub fn get_investment(env: Env, investment_id: u32) -> Result<Investment, Error> {
-        let investment_key = symbol_short!("INV");
-        let investments: Map<u32, Investment> = env.storage().instance().get(&investment_key).unwrap_or(Map::new(&env));
-        investments.get(investment_id).ok_or(Error::from_type_and_code(ScErrorType::Storage, ScErrorCode::MissingValue))
-    }
+    // Get the platform fee config applying to completions right now, if one is set
+    pub fn get_fee_config(env: Env) -> Result<Option<FeeConfig>, ContractError> {
+        Self::require_initialized(&env)?;
 
-    // Get all investments for a buyer
-    pub fn get_buyer_investments(env: Env, buyer: Address) -> Result<Vec<Investment>, Error> {
-        let investment_key = symbol_short!("INV");
-        let investments: Map<u32, Investment> = env.storage().instance().get(&investment_key).unwrap_or(Map::new(&env));
-        
-        let mut buyer_investments = vec![&env];
-        for (_, investment) in investments.iter() {
-            if investment.buyer == buyer {
-                buyer_investments.push_back(investment);
+        if let Some(scheduled) = Self::get_scheduled_fee_config(env.clone())? {
+            if env.ledger().timestamp() >= scheduled.effective_at {
+                return Ok(Some(scheduled.config));
             }
         }
-        
-        Ok(buyer_investments)
+        Ok(env.storage().instance().get(&symbol_short!("FEECFG")))
     }
 
-    // Update investment status (admin only)
-    pub fn update_investment_status(
-        env: Env,
-        investment_id: u32,
-        status: String,
-    ) -> Result<(), Error> {
-        let admin = Self::get_admin(env.clone())?;
-        if env.current_contract_address() != admin {
-            return Err(Error::from_type_and_code(ScErrorType::Auth, ScErrorCode::InvalidAction));
+    // Get the most recently scheduled fee change, whether or not it applies yet
+    pub fn get_scheduled_fee_config(env: Env) -> Result<Option<ScheduledFeeConfig>, ContractError> {
+        Self::require_initialized(&env)?;
+
+        Ok(env.storage().instance().get(&symbol_short!("FEENEXT")))
+    }
+
+    // Get the platform fees collected and not yet withdrawn
+    pub fn get_accrued_fees(env: Env) -> Result<i128, ContractError> {
+        Self::require_initialized(&env)?;
+
+        Ok(env.storage().instance().get(&symbol_short!("FEES")).unwrap_or(0))
+    }
+
+    // Withdraw accrued platform fees to the configured treasury (Treasurer)
+    pub fn withdraw_fees(env: Env, operator: Address, to: Address, amount: i128) -> Result<(), ContractError> {
+        Self::require_role(&env, &operator, Role::Treasurer, None)?;
+
+        let fee_config = Self::get_fee_config(env.clone())?.ok_or(ContractError::InvalidFeeConfig)?;
+        if to != fee_config.treasury {
+            return Err(ContractError::Unauthorized);
+        }
+        if amount <= 0 {
+            return Err(ContractError::BelowMinimum);
+        }
+        let fees = Self::get_accrued_fees(env.clone())?;
+        if amount > fees {
+            return Err(ContractError::InsufficientFees);
         }
 
-        let mut investment = Self::get_investment(env.clone(), investment_id)?;
-        investment.status = status.clone();
-        
-        let investment_key = symbol_short!("INV");
-        let mut investments: Map<u32, Investment> = env.storage().instance().get(&investment_key).unwrap_or(Map::new(&env));
-        investments.set(investment_id, investment);
-        env.storage().instance().set(&investment_key, &investments);
+        env.storage().instance().set(&symbol_short!("FEES"), &(fees - amount));
+        let payment_token = Self::get_payment_token(env.clone())?;
+        token::Client::new(&env, &payment_token).transfer(&env.current_contract_address(), &to, &amount);
+
+        env.events().publish((symbol_short!("FEEWDRAW"),), FeesWithdrawn { schema_version: EVENT_SCHEMA_VERSION, operator, to, amount });
+
+        Ok(())
+    }
+
+    // Give an account a role, across all tokens or for a single one (admin only)
+    pub fn grant_role(env: Env, role: Role, account: Address, token_id: Option<String>) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+
+        let role_key = (symbol_short!("ROLE"), role, account.clone(), token_id.clone());
+        env.storage().persistent().set(&role_key, &true);
+        env.storage().persistent().extend_ttl(&role_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
 
-        // Emit event
         env.events().publish(
-            (symbol_short!("INVSTAT"),),
-            (investment_id, status),
+            (symbol_short!("ROLE"), account.clone()),
+            RoleChanged { schema_version: EVENT_SCHEMA_VERSION, role, account, token_id, granted: true },
         );
 
         Ok(())
     }
 
-    // Get total investments for a token
-    pub fn get_token_total_investments(env: Env, token_id: String) -> Result<i128, Error> {
-        let investment_key = symbol_short!("INV");
-        let investments: Map<u32, Investment> = env.storage().instance().get(&investment_key).unwrap_or(Map::new(&env));
-        
-        let mut total = 0i128;
-        for (_, investment) in investments.iter() {
-            if investment.token_id == token_id && investment.status == String::from_str(&env, "completed") {
-                total += investment.amount;
-            }
+    // Take a role grant away; a global grant and a per-token grant are revoked separately (admin only)
+    pub fn revoke_role(env: Env, role: Role, account: Address, token_id: Option<String>) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+
+        env.storage().persistent().remove(&(symbol_short!("ROLE"), role, account.clone(), token_id.clone()));
+
+        env.events().publish(
+            (symbol_short!("ROLE"), account.clone()),
+            RoleChanged { schema_version: EVENT_SCHEMA_VERSION, role, account, token_id, granted: false },
+        );
+
+        Ok(())
+    }
+
+    // Check whether an account holds a role, either globally or for the given token
+    pub fn has_role(env: Env, role: Role, account: Address, token_id: Option<String>) -> Result<bool, ContractError> {
+        Self::require_initialized(&env)?;
+
+        if Self::has_role_grant(&env, role, &account, None) {
+            return Ok(true);
         }
-        
-        Ok(total)
+        Ok(token_id.is_some() && Self::has_role_grant(&env, role, &account, token_id))
     }
 
-    // Helper function to get next investment ID
-    fn get_next_investment_id(env: Env) -> Result<u32, Error> {
-        let counter_key = symbol_short!("CNT");
-        Ok(env.storage().instance().get(&counter_key).unwrap_or(1))
+    // Record an investor's KYC verification, or revoke it (Compliance)
+    pub fn set_kyc(env: Env, operator: Address, investor: Address, record: KycRecord) -> Result<(), ContractError> {
+        Self::require_role(&env, &operator, Role::Compliance, None)?;
+
+        let kyc_key = (symbol_short!("KYC"), investor.clone());
+        env.storage().persistent().set(&kyc_key, &record);
+        env.storage().persistent().extend_ttl(&kyc_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
+
+        env.events().publish((symbol_short!("KYC"), investor.clone()), KycUpdated { schema_version: EVENT_SCHEMA_VERSION, investor, record });
+
+        Ok(())
     }
 
-    // Get contract statistics
-    pub fn get_stats(env: Env) -> Result<Vec<i128>, Error> {
-        let investment_key = symbol_short!("INV");
-        let investments: Map<u32, Investment> = env.storage().instance().get(&investment_key).unwrap_or(Map::new(&env));
-        
-        let mut total_investments = 0i128;
-        let mut total_amount = 0i128;
-        let mut completed_investments = 0i128;
-        
-        for (_, investment) in investments.iter() {
-            total_investments += 1;
-            total_amount += investment.amount;
-            if investment.status == String::from_str(&env, "completed") {
-                completed_investments += 1;
-            }
+    // Get an investor's KYC record, if they have one
+    pub fn get_kyc(env: Env, investor: Address) -> Result<Option<KycRecord>, ContractError> {
+        Self::require_initialized(&env)?;
+
+        Ok(env.storage().persistent().get(&(symbol_short!("KYC"), investor)))
+    }
+
+    // Halt new investments and offering settlement across the contract (Pauser)
+    pub fn pause(env: Env, operator: Address) -> Result<(), ContractError> {
+        Self::require_role(&env, &operator, Role::Pauser, None)?;
+
+        env.storage().instance().set(&symbol_short!("PAUSED"), &true);
+
+        env.events().publish(
+            (symbol_short!("PAUSED"), None::<String>),
+            Paused { schema_version: EVENT_SCHEMA_VERSION, operator, token_id: None, paused: true },
+        );
+
+        Ok(())
+    }
+
+    // Lift a contract-wide pause (Pauser)
+    pub fn unpause(env: Env, operator: Address) -> Result<(), ContractError> {
+        Self::require_role(&env, &operator, Role::Pauser, None)?;
+
+        env.storage().instance().remove(&symbol_short!("PAUSED"));
+
+        env.events().publish(
+            (symbol_short!("PAUSED"), None::<String>),
+            Paused { schema_version: EVENT_SCHEMA_VERSION, operator, token_id: None, paused: false },
+        );
+
+        Ok(())
+    }
+
+    // Halt new investments and settlement for a single offering (Pauser for the token)
+    pub fn pause_token(env: Env, operator: Address, token_id: String) -> Result<(), ContractError> {
+        Self::require_role(&env, &operator, Role::Pauser, Some(token_id.clone()))?;
+
+        if !Self::has_token(&env, &token_id) {
+            return Err(ContractError::TokenNotFound);
         }
+        env.storage().instance().set(&(symbol_short!("PAUSED"), token_id.clone()), &true);
+
+        env.events().publish(
+            (symbol_short!("PAUSED"), Some(token_id.clone())),
+            Paused { schema_version: EVENT_SCHEMA_VERSION, operator, token_id: Some(token_id), paused: true },
+        );
+
+        Ok(())
+    }
+
+    // Lift the pause on a single offering (Pauser for the token)
+    pub fn unpause_token(env: Env, operator: Address, token_id: String) -> Result<(), ContractError> {
+        Self::require_role(&env, &operator, Role::Pauser, Some(token_id.clone()))?;
+
+        env.storage().instance().remove(&(symbol_short!("PAUSED"), token_id.clone()));
+
+        env.events().publish(
+            (symbol_short!("PAUSED"), Some(token_id.clone())),
+            Paused { schema_version: EVENT_SCHEMA_VERSION, operator, token_id: Some(token_id), paused: false },
+        );
+
+        Ok(())
+    }
+
+    // Check whether the whole contract is paused
+    pub fn is_paused(env: Env) -> Result<bool, ContractError> {
+        Self::require_initialized(&env)?;
+
+        Ok(env.storage().instance().has(&symbol_short!("PAUSED")))
+    }
+
+    // Check whether an offering is paused, either on its own or through a contract-wide pause
+    pub fn is_token_paused(env: Env, token_id: String) -> Result<bool, ContractError> {
+        Self::require_initialized(&env)?;
+
+        Ok(env.storage().instance().has(&symbol_short!("PAUSED"))
+            || env.storage().instance().has(&(symbol_short!("PAUSED"), token_id)))
+    }
+
+    // Register a new token offering (admin only)
+    pub fn register_token(env: Env, token: TokenInfo) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+
+        // A delisted id keeps its investments, yield and finalization state, so it cannot be reused
+        if Self::has_token(&env, &token.id) || Self::read_token_investment_count(&env, &token.id) > 0 {
+            return Err(ContractError::TokenAlreadyExists);
+        }
+        Self::validate_token(&token)?;
+        Self::validate_share_token(&env, &token.share_token)?;
+        Self::attach_share_token(&env, &token)?;
+        Self::write_token(&env, &token);
+
+        env.events().publish(
+            (symbol_short!("TOKENREG"), token.id.clone()),
+            TokenRegistered { schema_version: EVENT_SCHEMA_VERSION, token_id: token.id, name: token.name, share_token: token.share_token },
+        );
+        Ok(())
+    }
+
+    // Update token information (TokenManager for the token; changing the treasury or referral
+    // fee also needs Treasurer, and changing KYC or compliance settings needs Compliance)
+    pub fn update_token_info(env: Env, operator: Address, token: TokenInfo) -> Result<(), ContractError> {
+        Self::require_role(&env, &operator, Role::TokenManager, Some(token.id.clone()))?;
+
+        let current = Self::read_token(&env, &token.id)?;
+        if (token.treasury != current.treasury || token.referral_fee_bps != current.referral_fee_bps)
+            && !Self::holds_role(&env, &operator, Role::Treasurer, Some(token.id.clone()))?
+        {
+            return Err(ContractError::Unauthorized);
+        }
+        if (token.require_kyc != current.require_kyc
+            || token.blocked_jurisdictions != current.blocked_jurisdictions
+            || token.compliance_contract != current.compliance_contract)
+            && !Self::holds_role(&env, &operator, Role::Compliance, Some(token.id.clone()))?
+        {
+            return Err(ContractError::Unauthorized);
+        }
+        Self::validate_token(&token)?;
+        // Yield positions are kept in the registered share token's units, so it cannot be swapped
+        if token.share_token != current.share_token || token.target_raise < Self::read_raised(&env, &token.id) {
+            return Err(ContractError::InvalidTokenConfig);
+        }
+        // Once the sale has closed, its window and soft cap decide how finalization settles it
+        if (env.ledger().timestamp() >= current.sale_end || Self::is_finalizing(&env, &token.id))
+            && (token.sale_start != current.sale_start
+                || token.sale_end != current.sale_end
+                || token.soft_cap != current.soft_cap)
+        {
+            return Err(ContractError::InvalidTokenConfig);
+        }
+        Self::write_token(&env, &token);
+
+        env.events().publish(
+            (symbol_short!("TOKENUPD"), token.id.clone()),
+            TokenUpdated { schema_version: EVENT_SCHEMA_VERSION, token_id: token.id, operator },
+        );
+
+        Ok(())
+    }
+
+    // Remove a token offering from the registry (admin only)
+    pub fn delist_token(env: Env, token_id: String) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+
+        if !Self::has_token(&env, &token_id) {
+            return Err(ContractError::TokenNotFound);
+        }
+        // Pending investments still need the token to be cancelled, refunded or completed
+        let escrow: i128 = env.storage().instance().get(&(symbol_short!("ESCROW"), token_id.clone())).unwrap_or(0);
+        if escrow > 0 {
+            return Err(ContractError::EscrowOutstanding);
+        }
+        env.storage().instance().remove(&(symbol_short!("TOKEN"), token_id.clone()));
+
+        let tokens_key = symbol_short!("TOKENS");
+        let mut token_ids: Vec<String> = env.storage().instance().get(&tokens_key).unwrap_or(Vec::new(&env));
+        if let Some(index) = token_ids.first_index_of(&token_id) {
+            token_ids.remove(index);
+        }
+        env.storage().instance().set(&tokens_key, &token_ids);
+
+        env.events().publish((symbol_short!("TOKENDEL"), token_id.clone()), TokenDelisted { schema_version: EVENT_SCHEMA_VERSION, token_id });
+
+        Ok(())
+    }
+
+    // Get token information for a registered token
+    pub fn get_token_info(env: Env, token_id: String) -> Result<TokenView, ContractError> {
+        Self::require_initialized(&env)?;
+
+        let token = Self::read_token(&env, &token_id)?;
+        let raised = Self::read_raised(&env, &token_id);
+        let fill_bps = (raised * 10000 / token.target_raise) as u32;
+
+        Ok(TokenView { token, raised, fill_bps })
+    }
+
+    // List all registered tokens
+    pub fn list_tokens(env: Env) -> Result<Vec<TokenView>, ContractError> {
+        Self::require_initialized(&env)?;
+
+        let tokens_key = symbol_short!("TOKENS");
+        let token_ids: Vec<String> = env.storage().instance().get(&tokens_key).unwrap_or(Vec::new(&env));
+
+        let mut tokens = vec![&env];
+        for token_id in token_ids.iter() {
+            tokens.push_back(Self::get_token_info(env.clone(), token_id)?);
+        }
+
+        Ok(tokens)
+    }
+
+    // Register a widget hash issued by the backend for a site owner (admin or token manager)
+    pub fn register_widget(
+        env: Env,
+        operator: Address,
+        owner: Address,
+        widget_hash: String,
+        token_id: String,
+        payout: Address,
+    ) -> Result<(), ContractError> {
+        Self::require_initialized(&env)?;
+
+        Self::require_role(&env, &operator, Role::TokenManager, Some(token_id.clone()))?;
+
+        if !Self::has_token(&env, &token_id) {
+            return Err(ContractError::TokenNotFound);
+        }
+        let widget_key = (symbol_short!("WIDGET"), widget_hash.clone());
+        if env.storage().persistent().has(&widget_key) {
+            return Err(ContractError::WidgetAlreadyExists);
+        }
+
+        let widget = Widget {
+            owner: owner.clone(),
+            payout,
+            token_id: token_id.clone(),
+            active: true,
+            created_at: env.ledger().timestamp(),
+        };
+        Self::write_widget(&env, &widget_hash, &widget);
+
+        env.events().publish(
+            (symbol_short!("WIDGETREG"), token_id.clone()),
+            WidgetRegistered { schema_version: EVENT_SCHEMA_VERSION, widget_hash, token_id, owner, payout: widget.payout },
+        );
+
+        Ok(())
+    }
+
+    // Stop a widget from attributing new investments (widget owner only)
+    pub fn deactivate_widget(env: Env, widget_hash: String) -> Result<(), ContractError> {
+        let mut widget = Self::get_widget(env.clone(), widget_hash.clone())?;
+        widget.owner.require_auth();
+
+        widget.active = false;
+        Self::write_widget(&env, &widget_hash, &widget);
+
+        env.events().publish(
+            (symbol_short!("WIDGETOFF"), widget.token_id.clone()),
+            WidgetDeactivated { schema_version: EVENT_SCHEMA_VERSION, widget_hash, token_id: widget.token_id, owner: widget.owner },
+        );
+
+        Ok(())
+    }
+
+    // Pay out the referral fees accrued to a widget payout address (payout address only)
+    pub fn claim_referral_fees(env: Env, payout: Address) -> Result<i128, ContractError> {
+        Self::require_initialized(&env)?;
+
+        payout.require_auth();
+
+        let amount = Self::get_referral_balance(env.clone(), payout.clone())?;
+        if amount > 0 {
+            let payment_token = Self::get_payment_token(env.clone())?;
+            token::Client::new(&env, &payment_token).transfer(&env.current_contract_address(), &payout, &amount);
+        }
+        env.storage().persistent().remove(&(symbol_short!("REFERRAL"), payout.clone()));
+
+        env.events().publish((symbol_short!("REFCLAIM"), payout.clone()), ReferralClaimed { schema_version: EVENT_SCHEMA_VERSION, payout, amount });
+
+        Ok(amount)
+    }
+
+    // Get the referral fees accrued to a widget payout address
+    pub fn get_referral_balance(env: Env, payout: Address) -> Result<i128, ContractError> {
+        Self::require_initialized(&env)?;
+
+        Ok(env.storage().persistent().get(&(symbol_short!("REFERRAL"), payout)).unwrap_or(0))
+    }
+
+    // Get a registered widget
+    pub fn get_widget(env: Env, widget_hash: String) -> Result<Widget, ContractError> {
+        Self::require_initialized(&env)?;
+
+        let widget_key = (symbol_short!("WIDGET"), widget_hash);
+        let widget = env.storage().persistent().get(&widget_key).ok_or(ContractError::WidgetNotFound)?;
+        env.storage().persistent().extend_ttl(&widget_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
+        Ok(widget)
+    }
+
+    // Create a new investment, optionally attributed to the widget it came through
+    pub fn create_investment(
+        env: Env,
+        buyer: Address,
+        token_id: String,
+        amount: i128,
+        widget_hash: Option<String>,
+    ) -> Result<u32, ContractError> {
+        Self::require_initialized(&env)?;
+
+        buyer.require_auth();
+        Self::require_not_paused(&env, &token_id)?;
+
+        // Validate amount
+        let token_info = Self::read_token(&env, &token_id)?;
+        if amount < token_info.min_investment {
+            return Err(ContractError::BelowMinimum);
+        }
+        if amount > token_info.max_investment {
+            return Err(ContractError::AboveMaximum);
+        }
+        Self::check_kyc(&env, &buyer, &token_info)?;
+
+        if let Some(widget_hash) = &widget_hash {
+            let widget = Self::get_widget(env.clone(), widget_hash.clone())?;
+            if !widget.active {
+                return Err(ContractError::WidgetInactive);
+            }
+            if widget.token_id != token_id {
+                return Err(ContractError::WidgetTokenMismatch);
+            }
+        }
+
+        let now = env.ledger().timestamp();
+        if now < token_info.sale_start || now >= token_info.sale_end || Self::is_finalizing(&env, &token_id) {
+            return Err(ContractError::SaleNotOpen);
+        }
+
+        // Enforce the hard cap, filling only what is left when the offering allows it.
+        // A remainder below the minimum investment is not accepted either.
+        let raised = Self::read_raised(&env, &token_id);
+        let remaining = token_info.target_raise - raised;
+        let amount = if amount <= remaining {
+            amount
+        } else if token_info.allow_partial_fill && remaining >= token_info.min_investment {
+            remaining
+        } else {
+            return Err(ContractError::RaiseCapExceeded);
+        };
+
+        // Ask the issuer's compliance registry about the amount actually being invested
+        if let Some(compliance_contract) = &token_info.compliance_contract {
+            if !ComplianceClient::new(&env, compliance_contract).is_eligible(&buyer, &token_id, &amount) {
+                return Err(ContractError::NotEligible);
+            }
+        }
+
+        // Enforce the cumulative per-investor limit on what actually gets invested
+        let investor_total = Self::read_investor_total(&env, &buyer, &token_id);
+        if investor_total + amount > token_info.max_per_investor {
+            return Err(ContractError::InvestorLimitExceeded);
+        }
+        Self::write_investor_total(&env, &buyer, &token_id, investor_total + amount);
+        Self::write_raised(&env, &token_id, raised + amount);
+
+        // Hold the payment in contract escrow while the investment is pending
+        let payment_token = Self::get_payment_token(env.clone())?;
+        token::Client::new(&env, &payment_token).transfer(
+            &buyer,
+            &env.current_contract_address(),
+            &amount,
+        );
+        let escrow_key = (symbol_short!("ESCROW"), token_id.clone());
+        let escrowed: i128 = env.storage().instance().get(&escrow_key).unwrap_or(0);
+        env.storage().instance().set(&escrow_key, &(escrowed + amount));
+
+        // Create investment record
+        let investment = Investment {
+            buyer: buyer.clone(),
+            token_id: token_id.clone(),
+            amount,
+            timestamp: env.ledger().timestamp(),
+            status: InvestmentStatus::Pending,
+            shares: 0,
+            widget_hash: widget_hash.clone(),
+        };
+
+        // Store investment
+        let investment_id = Self::get_next_investment_id(env.clone())?;
+        Self::write_investment(&env, investment_id, &investment);
+        Self::index_buyer_investment(&env, &buyer, investment_id);
+        Self::index_token_investment(&env, &token_id, investment_id);
+
+        // Update investment counter and running totals
+        let counter_key = symbol_short!("CNT");
+        let next_id = investment_id + 1;
+        env.storage().instance().set(&counter_key, &next_id);
+
+        let amount_key = symbol_short!("TOTAMT");
+        let total_amount: i128 = env.storage().instance().get(&amount_key).unwrap_or(0);
+        env.storage().instance().set(&amount_key, &(total_amount + amount));
+        Self::extend_instance(&env);
+
+        // Emit event
+        env.events().publish(
+            (symbol_short!("INVESTED"), token_id.clone(), buyer.clone()),
+            InvestmentCreated { schema_version: EVENT_SCHEMA_VERSION, investment_id, token_id, buyer, amount, widget_hash },
+        );
+
+        Ok(investment_id)
+    }
+
+    // Get investment by ID
+    pub fn get_investment(env: Env, investment_id: u32) -> Result<Investment, ContractError> {
+        Self::require_initialized(&env)?;
+
+        Self::read_investment(&env, investment_id).ok_or(ContractError::InvestmentNotFound)
+    }
+
+    // Get a page of a buyer's investment ids, starting at the given cursor
+    pub fn get_buyer_investments(
+        env: Env,
+        buyer: Address,
+        start: u32,
+        limit: u32,
+    ) -> Result<InvestmentPage, ContractError> {
+        Self::require_initialized(&env)?;
+
+        if limit == 0 || limit > MAX_PAGE_SIZE {
+            return Err(ContractError::InvalidPageSize);
+        }
+
+        let investment_ids = Self::read_buyer_investments(&env, &buyer);
+        let len = investment_ids.len();
+        let end = start.saturating_add(limit).min(len);
+
+        let ids = if start < end { investment_ids.slice(start..end) } else { Vec::new(&env) };
+        let next_cursor = if end < len { Some(end) } else { None };
+
+        Ok(InvestmentPage { ids, next_cursor })
+    }
+
+    // Get the number of investments a buyer has made
+    pub fn get_buyer_investment_count(env: Env, buyer: Address) -> Result<u32, ContractError> {
+        Self::require_initialized(&env)?;
+
+        Ok(Self::read_buyer_investments(&env, &buyer).len())
+    }
+
+    // Update investment status (StatusUpdater for the token)
+    pub fn update_investment_status(
+        env: Env,
+        operator: Address,
+        investment_id: u32,
+        status: InvestmentStatus,
+    ) -> Result<(), ContractError> {
+        let investment = Self::get_investment(env.clone(), investment_id)?;
+        Self::require_role(&env, &operator, Role::StatusUpdater, Some(investment.token_id.clone()))?;
+
+        // Completing releases escrow and mints shares, so it halts with the offering. A soft-capped
+        // offering also only releases escrow once finalization has found the cap met.
+        if status == InvestmentStatus::Completed {
+            Self::require_not_paused(&env, &investment.token_id)?;
+            let token_info = Self::read_token(&env, &investment.token_id)?;
+            let finalization: Option<Finalization> =
+                env.storage().instance().get(&(symbol_short!("FINAL"), investment.token_id.clone()));
+            if token_info.soft_cap > 0 && !finalization.is_some_and(|finalization| finalization.soft_cap_met) {
+                return Err(ContractError::SoftCapNotMet);
+            }
+        }
+
+        Self::transition_investment(&env, investment_id, investment, status)
+    }
+
+    // Refund a pending investment the admin has not acted on (buyer only)
+    pub fn claim_refund(env: Env, buyer: Address, investment_id: u32) -> Result<(), ContractError> {
+        Self::require_initialized(&env)?;
+
+        buyer.require_auth();
+
+        let investment = Self::get_investment(env.clone(), investment_id)?;
+        if investment.buyer != buyer {
+            return Err(ContractError::Unauthorized);
+        }
+        if investment.status != InvestmentStatus::Pending
+            || env.ledger().timestamp() < investment.timestamp + REFUND_DELAY
+            || Self::is_finalizing(&env, &investment.token_id)
+        {
+            return Err(ContractError::RefundNotAvailable);
+        }
+
+        Self::transition_investment(&env, investment_id, investment, InvestmentStatus::Refunded)
+    }
+
+    // Cancel a pending investment within the token's cancellation window (buyer only)
+    pub fn cancel_investment(env: Env, buyer: Address, investment_id: u32) -> Result<(), ContractError> {
+        Self::require_initialized(&env)?;
+
+        buyer.require_auth();
+
+        let investment = Self::get_investment(env.clone(), investment_id)?;
+        if investment.buyer != buyer {
+            return Err(ContractError::Unauthorized);
+        }
+        if investment.status != InvestmentStatus::Pending {
+            return Err(ContractError::InvalidStatus);
+        }
+
+        // Once the sale has closed, the amount raised decides the soft cap and must not drop
+        let token_info = Self::read_token(&env, &investment.token_id)?;
+        let now = env.ledger().timestamp();
+        if now >= investment.timestamp + token_info.cancel_window
+            || now >= token_info.sale_end
+            || Self::is_finalizing(&env, &investment.token_id)
+        {
+            return Err(ContractError::CancellationWindowClosed);
+        }
+
+        Self::transition_investment(&env, investment_id, investment, InvestmentStatus::Cancelled)
+    }
+
+    // Settle the next batch of a closed offering's pending investments: all complete
+    // if the soft cap was met, otherwise all are refunded. Call until done.
+    pub fn finalize_offering(env: Env, token_id: String) -> Result<Finalization, ContractError> {
+        Self::require_initialized(&env)?;
+        Self::require_not_paused(&env, &token_id)?;
+
+        let token_info = Self::read_token(&env, &token_id)?;
+        if env.ledger().timestamp() < token_info.sale_end {
+            return Err(ContractError::SaleNotEnded);
+        }
+
+        let finalization_key = (symbol_short!("FINAL"), token_id.clone());
+        let mut finalization = env.storage().instance().get(&finalization_key).unwrap_or(Finalization {
+            soft_cap_met: Self::read_raised(&env, &token_id) >= token_info.soft_cap,
+            cursor: 0,
+            done: false,
+        });
+        if finalization.done {
+            return Ok(finalization);
+        }
+
+        let status = if finalization.soft_cap_met {
+            InvestmentStatus::Completed
+        } else {
+            InvestmentStatus::Failed
+        };
+        let count = Self::read_token_investment_count(&env, &token_id);
+        let end = finalization.cursor.saturating_add(FINALIZE_BATCH_SIZE).min(count);
+        for position in finalization.cursor..end {
+            let investment_id = Self::read_token_investment(&env, &token_id, position);
+            let investment = Self::get_investment(env.clone(), investment_id)?;
+            if investment.status == InvestmentStatus::Pending {
+                Self::transition_investment(&env, investment_id, investment, status)?;
+            }
+        }
+        finalization.cursor = end;
+        finalization.done = end == count;
+        env.storage().instance().set(&finalization_key, &finalization);
+
+        if finalization.done {
+            env.events().publish(
+                (symbol_short!("FINALIZED"), token_id.clone()),
+                OfferingFinalized { schema_version: EVENT_SCHEMA_VERSION, token_id, soft_cap_met: finalization.soft_cap_met },
+            );
+        }
+
+        Ok(finalization)
+    }
+
+    // Get the finalization progress of an offering, if it has started
+    pub fn get_finalization(env: Env, token_id: String) -> Result<Option<Finalization>, ContractError> {
+        Self::require_initialized(&env)?;
+
+        Ok(env.storage().instance().get(&(symbol_short!("FINAL"), token_id)))
+    }
+
+    // Get a buyer's running total in a token, excluding refunded and cancelled investments
+    pub fn get_investor_total(env: Env, buyer: Address, token_id: String) -> Result<i128, ContractError> {
+        Self::require_initialized(&env)?;
+
+        Ok(Self::read_investor_total(&env, &buyer, &token_id))
+    }
+
+    // Get the amount held in escrow for a token's pending investments
+    pub fn get_escrow_balance(env: Env, token_id: String) -> Result<i128, ContractError> {
+        Self::require_initialized(&env)?;
+
+        let escrow_key = (symbol_short!("ESCROW"), token_id);
+        Ok(env.storage().instance().get(&escrow_key).unwrap_or(0))
+    }
+
+    // Get total investments for a token
+    pub fn get_token_total_investments(env: Env, token_id: String) -> Result<i128, ContractError> {
+        Self::require_initialized(&env)?;
+
+        let token_total_key = (symbol_short!("TOKTOTAL"), token_id);
+        Ok(env.storage().instance().get(&token_total_key).unwrap_or(0))
+    }
+
+    // Get what a token's completed investments should earn in a year at its advertised APY
+    pub fn get_expected_annual_yield(env: Env, token_id: String) -> Result<i128, ContractError> {
+        Self::require_initialized(&env)?;
+        let token_info = Self::read_token(&env, &token_id)?;
+        let completed = Self::get_token_total_investments(env, token_id)?;
+
+        Ok(completed * token_info.apy / 10000)
+    }
+
+    // Deposit yield in the payment asset, split pro rata over the token's completed
+    // investments at the time of the deposit (Treasurer for the token)
+    pub fn deposit_yield(env: Env, operator: Address, token_id: String, amount: i128) -> Result<(), ContractError> {
+        Self::require_role(&env, &operator, Role::Treasurer, Some(token_id.clone()))?;
+
+        let token_info = Self::read_token(&env, &token_id)?;
+        if amount <= 0 {
+            return Err(ContractError::BelowMinimum);
+        }
+        let shares = ShareTokenClient::new(&env, &token_info.share_token).total_supply();
+        if shares == 0 {
+            return Err(ContractError::NoYieldHolders);
+        }
+
+        let payment_token = Self::get_payment_token(env.clone())?;
+        token::Client::new(&env, &payment_token).transfer(&operator, &env.current_contract_address(), &amount);
+
+        // Whatever does not divide evenly over the shares carries into the next deposit
+        let accumulator_key = (symbol_short!("YIELDACC"), token_id.clone());
+        let remainder_key = (symbol_short!("YIELDREM"), token_id.clone());
+        let accumulator: i128 = env.storage().instance().get(&accumulator_key).unwrap_or(0);
+        let remainder: i128 = env.storage().instance().get(&remainder_key).unwrap_or(0);
+        let scaled = amount * Self::yield_precision(&env, &token_id) + remainder;
+        env.storage().instance().set(&accumulator_key, &(accumulator + scaled / shares));
+        env.storage().instance().set(&remainder_key, &(scaled % shares));
+        Self::extend_instance(&env);
+
+        env.events().publish((symbol_short!("YIELDDEP"), token_id.clone()), YieldDeposited { schema_version: EVENT_SCHEMA_VERSION, token_id, operator, amount });
+
+        Ok(())
+    }
+
+    // Pay out a buyer's accrued yield for a token (buyer only)
+    pub fn claim_yield(env: Env, buyer: Address, token_id: String) -> Result<i128, ContractError> {
+        Self::require_initialized(&env)?;
+
+        buyer.require_auth();
+
+        let mut position = Self::settle_yield(&env, &buyer, &token_id);
+        let amount = position.owed;
+        if amount > 0 {
+            let payment_token = Self::get_payment_token(env.clone())?;
+            token::Client::new(&env, &payment_token).transfer(&env.current_contract_address(), &buyer, &amount);
+        }
+        position.owed = 0;
+        Self::write_yield_position(&env, &buyer, &token_id, &position);
+
+        env.events().publish(
+            (symbol_short!("YIELDCLM"), token_id.clone(), buyer.clone()),
+            YieldClaimed { schema_version: EVENT_SCHEMA_VERSION, token_id, buyer, amount },
+        );
+
+        Ok(amount)
+    }
+
+    // Get the yield a buyer can claim for a token
+    pub fn pending_yield(env: Env, buyer: Address, token_id: String) -> Result<i128, ContractError> {
+        Self::require_initialized(&env)?;
+
+        Ok(Self::settle_yield(&env, &buyer, &token_id).owed)
+    }
+
+    // Move a yield position along with shares transferred or burned by their holder (share token only)
+    pub fn on_share_transfer(
+        env: Env,
+        share_token: Address,
+        from: Address,
+        to: Option<Address>,
+        amount: i128,
+    ) -> Result<(), ContractError> {
+        share_token.require_auth();
+
+        let token_id: String = env
+            .storage()
+            .instance()
+            .get(&(symbol_short!("SHARES"), share_token))
+            .ok_or(ContractError::TokenNotFound)?;
+        Self::add_yield_balance(&env, &from, &token_id, -amount);
+        if let Some(to) = to {
+            Self::add_yield_balance(&env, &to, &token_id, amount);
+        }
+
+        Ok(())
+    }
+
+    // Helper function to reject calls made before init
+    fn require_initialized(env: &Env) -> Result<(), ContractError> {
+        let init_key = symbol_short!("INIT");
+        if !env.storage().instance().has(&init_key) {
+            return Err(ContractError::NotInitialized);
+        }
+        Ok(())
+    }
+
+    // Helper function to require the stored admin's authorization
+    fn require_admin(env: &Env) -> Result<Address, ContractError> {
+        let admin = Self::get_admin(env.clone())?;
+        admin.require_auth();
+        Ok(admin)
+    }
+
+    // Helper function to reject calls while the contract or the given offering is paused
+    fn require_not_paused(env: &Env, token_id: &String) -> Result<(), ContractError> {
+        if Self::is_token_paused(env.clone(), token_id.clone())? {
+            return Err(ContractError::Paused);
+        }
+        Ok(())
+    }
+
+    // Helper function to require an operator's authorization and that they are the admin or hold the role
+    fn require_role(env: &Env, operator: &Address, role: Role, token_id: Option<String>) -> Result<(), ContractError> {
+        operator.require_auth();
+
+        if Self::holds_role(env, operator, role, token_id)? {
+            return Ok(());
+        }
+        Err(ContractError::Unauthorized)
+    }
+
+    // Helper function to check whether an already authorized operator is the admin or holds a role
+    fn holds_role(env: &Env, operator: &Address, role: Role, token_id: Option<String>) -> Result<bool, ContractError> {
+        Ok(*operator == Self::get_admin(env.clone())? || Self::has_role(env.clone(), role, operator.clone(), token_id)?)
+    }
+
+    // Helper function to look up one role grant, keeping it alive while it is in use
+    fn has_role_grant(env: &Env, role: Role, account: &Address, token_id: Option<String>) -> bool {
+        let role_key = (symbol_short!("ROLE"), role, account.clone(), token_id);
+        let granted = env.storage().persistent().has(&role_key);
+        if granted {
+            env.storage().persistent().extend_ttl(&role_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
+        }
+        granted
+    }
+
+    // Helper function to check a buyer against a token's KYC requirements
+    fn check_kyc(env: &Env, buyer: &Address, token_info: &TokenInfo) -> Result<(), ContractError> {
+        if !token_info.require_kyc {
+            return Ok(());
+        }
+
+        let record = Self::get_kyc(env.clone(), buyer.clone())?.ok_or(ContractError::NotVerified)?;
+        if record.status != KycStatus::Verified {
+            return Err(ContractError::NotVerified);
+        }
+        if env.ledger().timestamp() >= record.expires_at {
+            return Err(ContractError::KycExpired);
+        }
+        if token_info.blocked_jurisdictions.contains(&record.jurisdiction) {
+            return Err(ContractError::JurisdictionBlocked);
+        }
+        Ok(())
+    }
+
+    // Helper function to check whether a token is registered
+    fn has_token(env: &Env, token_id: &String) -> bool {
+        env.storage().instance().has(&(symbol_short!("TOKEN"), token_id.clone()))
+    }
+
+    // Helper function to check whether finalize_offering has started settling a token
+    fn is_finalizing(env: &Env, token_id: &String) -> bool {
+        env.storage().instance().has(&(symbol_short!("FINAL"), token_id.clone()))
+    }
+
+    // Helper function to read a registered token
+    fn read_token(env: &Env, token_id: &String) -> Result<TokenInfo, ContractError> {
+        env.storage().instance().get(&(symbol_short!("TOKEN"), token_id.clone())).ok_or(ContractError::TokenNotFound)
+    }
+
+    // Helper function to store a token and add it to the registry index
+    fn write_token(env: &Env, token: &TokenInfo) {
+        let tokens_key = symbol_short!("TOKENS");
+        let mut token_ids: Vec<String> = env.storage().instance().get(&tokens_key).unwrap_or(Vec::new(env));
+        if !token_ids.contains(&token.id) {
+            token_ids.push_back(token.id.clone());
+            env.storage().instance().set(&tokens_key, &token_ids);
+        }
+
+        env.storage().instance().set(&(symbol_short!("TOKEN"), token.id.clone()), token);
+    }
+
+    // Helper function to read the live amount raised by a token
+    fn read_raised(env: &Env, token_id: &String) -> i128 {
+        env.storage().instance().get(&(symbol_short!("RAISED"), token_id.clone())).unwrap_or(0)
+    }
+
+    // Helper function to write the live amount raised by a token
+    fn write_raised(env: &Env, token_id: &String, raised: i128) {
+        env.storage().instance().set(&(symbol_short!("RAISED"), token_id.clone()), &raised);
+    }
+
+    // Helper function to validate investment limits of a token
+    fn validate_token(token: &TokenInfo) -> Result<(), ContractError> {
+        if token.min_investment <= 0
+            || token.max_investment < token.min_investment
+            || token.max_per_investor < token.min_investment
+            || token.share_price <= 0
+            || token.target_raise <= 0
+            || token.sale_end <= token.sale_start
+            || token.cancel_window > MAX_CANCEL_WINDOW
+            || token.soft_cap < 0
+            || token.soft_cap > token.target_raise
+            || (!token.require_kyc && !token.blocked_jurisdictions.is_empty())
+            || (token.require_kyc && token.compliance_contract.is_some())
+            || token.referral_fee_bps > MAX_REFERRAL_FEE_BPS
+        {
+            return Err(ContractError::InvalidTokenConfig);
+        }
+        Ok(())
+    }
+
+    // Helper function to check a share token answers the calls completion makes, with this contract as its mint authority
+    fn validate_share_token(env: &Env, share_token: &Address) -> Result<(), ContractError> {
+        let client = ShareTokenClient::new(env, share_token);
+        match (client.try_admin(), client.try_decimals()) {
+            (Ok(Ok(admin)), Ok(Ok(decimals))) if admin == env.current_contract_address() && decimals <= MAX_SHARE_DECIMALS => Ok(()),
+            _ => Err(ContractError::InvalidShareToken),
+        }
+    }
+
+    // Helper function to have a share token report transfers here and remember which token it backs
+    fn attach_share_token(env: &Env, token: &TokenInfo) -> Result<(), ContractError> {
+        let shares_key = (symbol_short!("SHARES"), token.share_token.clone());
+        if env.storage().instance().has(&shares_key) {
+            return Err(ContractError::InvalidShareToken);
+        }
+        let client = ShareTokenClient::new(env, &token.share_token);
+        if !matches!(client.try_set_transfer_hook(&env.current_contract_address()), Ok(Ok(()))) {
+            return Err(ContractError::InvalidShareToken);
+        }
+        env.storage().instance().set(&shares_key, &token.id);
+        let precision = YIELD_PRECISION * 10i128.pow(client.decimals());
+        env.storage().instance().set(&(symbol_short!("YIELDPRE"), token.id.clone()), &precision);
+        Ok(())
+    }
+
+    // Helper function to mint shares for a completed amount at the token's price and start them earning yield
+    fn issue_shares(env: &Env, token_info: &TokenInfo, buyer: &Address, amount: i128) -> i128 {
+        let share_token = ShareTokenClient::new(env, &token_info.share_token);
+        let unit = 10i128.pow(share_token.decimals());
+        let shares = amount * unit / token_info.share_price;
+        share_token.mint(buyer, &shares);
+        Self::add_yield_balance(env, buyer, &token_info.id, shares);
+        shares
+    }
+
+    // Helper function to move an investment to a new status and settle its escrowed funds
+    fn transition_investment(
+        env: &Env,
+        investment_id: u32,
+        mut investment: Investment,
+        status: InvestmentStatus,
+    ) -> Result<(), ContractError> {
+        let old_status = investment.status;
+        if !old_status.can_transition_to(status) {
+            return Err(ContractError::InvalidStatus);
+        }
+
+        // Completed releases the escrow to the project treasury, less the referral fee owed to the
+        // widget it came through, and mints shares to the buyer; anything else refunds the buyer
+        let payment_token = token::Client::new(env, &Self::get_payment_token(env.clone())?);
+        let mut settlement = Settlement {
+            refunded: investment.amount,
+            proceeds: 0,
+            referral_fee: 0,
+            referral_payout: None,
+            platform_fee: 0,
+        };
+        if status == InvestmentStatus::Completed {
+            let token_info = Self::read_token(env, &investment.token_id)?;
+            investment.shares = Self::issue_shares(env, &token_info, &investment.buyer, investment.amount);
+
+            let (referral_fee, referral_payout) = match &investment.widget_hash {
+                Some(widget_hash) if token_info.referral_fee_bps > 0 => {
+                    let widget = Self::get_widget(env.clone(), widget_hash.clone())?;
+                    // A buyer cannot refer themselves and take a cut of their own investment
+                    if investment.buyer == widget.owner || investment.buyer == widget.payout {
+                        (0, None)
+                    } else {
+                        let referral_fee = investment.amount * i128::from(token_info.referral_fee_bps) / 10000;
+                        Self::accrue_referral_fee(env, &widget.payout, referral_fee);
+                        (referral_fee, Some(widget.payout))
+                    }
+                }
+                _ => (0, None),
+            };
+            let platform_fee = match Self::get_fee_config(env.clone())? {
+                Some(fee_config) => investment.amount * i128::from(fee_config.fee_bps) / 10000,
+                None => 0,
+            };
+            let fees: i128 = env.storage().instance().get(&symbol_short!("FEES")).unwrap_or(0);
+            env.storage().instance().set(&symbol_short!("FEES"), &(fees + platform_fee));
+
+            let proceeds = investment.amount - referral_fee - platform_fee;
+            payment_token.transfer(&env.current_contract_address(), &token_info.treasury, &proceeds);
+            settlement = Settlement { refunded: 0, proceeds, referral_fee, referral_payout, platform_fee };
+        } else {
+            payment_token.transfer(&env.current_contract_address(), &investment.buyer, &investment.amount);
+        }
+
+        let escrow_key = (symbol_short!("ESCROW"), investment.token_id.clone());
+        let escrowed: i128 = env.storage().instance().get(&escrow_key).unwrap_or(0);
+        env.storage().instance().set(&escrow_key, &(escrowed - investment.amount));
+
+        // Refunded money no longer counts toward the raise or the buyer's limit
+        if status != InvestmentStatus::Completed {
+            let raised = Self::read_raised(env, &investment.token_id);
+            Self::write_raised(env, &investment.token_id, raised - investment.amount);
+
+            let investor_total = Self::read_investor_total(env, &investment.buyer, &investment.token_id);
+            Self::write_investor_total(env, &investment.buyer, &investment.token_id, investor_total - investment.amount);
+        }
+
+        investment.status = status;
+        Self::write_investment(env, investment_id, &investment);
+
+        if status == InvestmentStatus::Completed {
+            let completed_key = symbol_short!("DONECNT");
+            let completed: i128 = env.storage().instance().get(&completed_key).unwrap_or(0);
+            env.storage().instance().set(&completed_key, &(completed + 1));
+
+            let token_total_key = (symbol_short!("TOKTOTAL"), investment.token_id.clone());
+            let token_total: i128 = env.storage().instance().get(&token_total_key).unwrap_or(0);
+            env.storage().instance().set(&token_total_key, &(token_total + investment.amount));
+        }
+        Self::extend_instance(env);
+
+        // Emit event
+        env.events().publish(
+            (symbol_short!("INVSTAT"), investment.token_id.clone(), investment.buyer.clone()),
+            StatusChanged {
+                schema_version: EVENT_SCHEMA_VERSION,
+                investment_id,
+                token_id: investment.token_id,
+                buyer: investment.buyer,
+                old_status,
+                new_status: status,
+                settlement,
+            },
+        );
+
+        Ok(())
+    }
+
+    // Helper function to read an investment from its own persistent entry
+    fn read_investment(env: &Env, investment_id: u32) -> Option<Investment> {
+        let investment_key = (symbol_short!("INV"), investment_id);
+        let investment = env.storage().persistent().get(&investment_key);
+        if investment.is_some() {
+            env.storage().persistent().extend_ttl(&investment_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
+        }
+        investment
+    }
+
+    // Helper function to write an investment to its own persistent entry
+    fn write_investment(env: &Env, investment_id: u32, investment: &Investment) {
+        let investment_key = (symbol_short!("INV"), investment_id);
+        env.storage().persistent().set(&investment_key, investment);
+        env.storage().persistent().extend_ttl(&investment_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
+    }
+
+    // Helper function to read a buyer's investment id index
+    fn read_buyer_investments(env: &Env, buyer: &Address) -> Vec<u32> {
+        let buyer_key = (symbol_short!("BUYER"), buyer.clone());
+        let investment_ids = env.storage().persistent().get(&buyer_key);
+        if investment_ids.is_some() {
+            env.storage().persistent().extend_ttl(&buyer_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
+        }
+        investment_ids.unwrap_or(Vec::new(env))
+    }
+
+    // Helper function to append an investment to its buyer's index
+    fn index_buyer_investment(env: &Env, buyer: &Address, investment_id: u32) {
+        let buyer_key = (symbol_short!("BUYER"), buyer.clone());
+        let mut investment_ids = Self::read_buyer_investments(env, buyer);
+        investment_ids.push_back(investment_id);
+        env.storage().persistent().set(&buyer_key, &investment_ids);
+        env.storage().persistent().extend_ttl(&buyer_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
+    }
+
+    // Helper function to read a buyer's running total invested in a token
+    fn read_investor_total(env: &Env, buyer: &Address, token_id: &String) -> i128 {
+        let total_key = (symbol_short!("INVTOTAL"), buyer.clone(), token_id.clone());
+        match env.storage().persistent().get::<_, i128>(&total_key) {
+            Some(total) => {
+                env.storage().persistent().extend_ttl(&total_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
+                total
+            }
+            None => 0,
+        }
+    }
+
+    // Helper function to write a buyer's running total invested in a token
+    fn write_investor_total(env: &Env, buyer: &Address, token_id: &String, total: i128) {
+        let total_key = (symbol_short!("INVTOTAL"), buyer.clone(), token_id.clone());
+        env.storage().persistent().set(&total_key, &total);
+        env.storage().persistent().extend_ttl(&total_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
+    }
+
+    // Helper function to read how many investments a token has received
+    fn read_token_investment_count(env: &Env, token_id: &String) -> u32 {
+        env.storage().instance().get(&(symbol_short!("TOKINVCNT"), token_id.clone())).unwrap_or(0)
+    }
+
+    // Helper function to read the investment id at a position in a token's index
+    fn read_token_investment(env: &Env, token_id: &String, position: u32) -> u32 {
+        let index_key = (symbol_short!("TOKINV"), token_id.clone(), position);
+        env.storage().persistent().extend_ttl(&index_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
+        env.storage().persistent().get(&index_key).unwrap_or(0)
+    }
+
+    // Helper function to append an investment to its token's index, one entry per position
+    fn index_token_investment(env: &Env, token_id: &String, investment_id: u32) {
+        let position = Self::read_token_investment_count(env, token_id);
+        let index_key = (symbol_short!("TOKINV"), token_id.clone(), position);
+        env.storage().persistent().set(&index_key, &investment_id);
+        env.storage().persistent().extend_ttl(&index_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
+        env.storage().instance().set(&(symbol_short!("TOKINVCNT"), token_id.clone()), &(position + 1));
+    }
+
+    // Helper function to record the payment asset and move the version 1 `TOKEN` slot into the
+    // token registry, returning the offering
+    fn migrate_token_slot(env: &Env, legacy_config: LegacyConfig) -> Result<TokenInfo, ContractError> {
+        let token_key = symbol_short!("TOKEN");
+        let legacy: LegacyTokenInfo = env.storage().instance().get(&token_key).ok_or(ContractError::TokenNotFound)?;
+        let token = TokenInfo {
+            id: legacy.id,
+            name: legacy.name,
+            apy: legacy.apy,
+            target_raise: legacy.total_value_locked,
+            min_investment: legacy.min_investment,
+            max_investment: legacy.max_investment,
+            ..legacy_config.token
+        };
+        Self::validate_token(&token)?;
+        Self::validate_share_token(env, &token.share_token)?;
+        Self::attach_share_token(env, &token)?;
+
+        env.storage().instance().set(&symbol_short!("PAYMENT"), &legacy_config.payment_token);
+        Self::write_token(env, &token);
+        env.storage().instance().remove(&token_key);
+
+        Ok(token)
+    }
+
+    // Helper function to move the next batch of the version 1 instance `INV` map, from cursor,
+    // into per-investment persistent entries, rebuilding the indexes and running totals from
+    // it. Returns the cursor to resume from, or None once the map has been moved.
+    fn migrate_investment_map(env: &Env, token_info: &TokenInfo, cursor: u32) -> Option<u32> {
+        let token_id = &token_info.id;
+        let investment_key = symbol_short!("INV");
+        let investments: Map<u32, LegacyInvestment> = env.storage().instance().get(&investment_key).unwrap_or(Map::new(env));
+        let investment_ids = investments.keys();
+        let end = cursor.saturating_add(MIGRATE_BATCH_SIZE).min(investment_ids.len());
+
+        let mut total_amount: i128 = env.storage().instance().get(&symbol_short!("TOTAMT")).unwrap_or(0);
+        let mut completed: i128 = env.storage().instance().get(&symbol_short!("DONECNT")).unwrap_or(0);
+        for position in cursor..end {
+            let investment_id = investment_ids.get_unchecked(position);
+            let legacy = investments.get_unchecked(investment_id);
+            // Version 1 never collected payment, so its open records have nothing in escrow to settle
+            let status = if legacy.status == String::from_str(env, "completed") {
+                InvestmentStatus::Completed
+            } else {
+                InvestmentStatus::Failed
+            };
+            // Version 1 did not check token ids, so every record belongs to its one offering
+            let mut investment = Investment {
+                buyer: legacy.buyer,
+                token_id: token_id.clone(),
+                amount: legacy.amount,
+                timestamp: legacy.timestamp,
+                status,
+                shares: 0,
+                widget_hash: None,
+            };
+
+            Self::index_buyer_investment(env, &investment.buyer, investment_id);
+            Self::index_token_investment(env, &investment.token_id, investment_id);
+
+            total_amount += investment.amount;
+            if status == InvestmentStatus::Completed {
+                completed += 1;
+
+                let raised = Self::read_raised(env, token_id);
+                Self::write_raised(env, token_id, raised + investment.amount);
+                let investor_total = Self::read_investor_total(env, &investment.buyer, token_id);
+                Self::write_investor_total(env, &investment.buyer, token_id, investor_total + investment.amount);
+
+                let token_total_key = (symbol_short!("TOKTOTAL"), investment.token_id.clone());
+                let token_total: i128 = env.storage().instance().get(&token_total_key).unwrap_or(0);
+                env.storage().instance().set(&token_total_key, &(token_total + investment.amount));
+
+                // Version 1 never issued shares, and yield is only paid on shares
+                investment.shares = Self::issue_shares(env, token_info, &investment.buyer, investment.amount);
+            }
+            Self::write_investment(env, investment_id, &investment);
+        }
+
+        env.storage().instance().set(&symbol_short!("TOTAMT"), &total_amount);
+        env.storage().instance().set(&symbol_short!("DONECNT"), &completed);
+        if end < investment_ids.len() {
+            return Some(end);
+        }
+        env.storage().instance().remove(&investment_key);
+        None
+    }
+
+    // Helper function to read a buyer's yield position in a token
+    fn read_yield_position(env: &Env, buyer: &Address, token_id: &String) -> YieldPosition {
+        let position_key = (symbol_short!("YIELD"), buyer.clone(), token_id.clone());
+        match env.storage().persistent().get::<_, YieldPosition>(&position_key) {
+            Some(position) => {
+                env.storage().persistent().extend_ttl(&position_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
+                position
+            }
+            None => YieldPosition { balance: 0, reward_debt: 0, owed: 0 },
+        }
+    }
+
+    // Helper function to write a buyer's yield position in a token
+    fn write_yield_position(env: &Env, buyer: &Address, token_id: &String, position: &YieldPosition) {
+        let position_key = (symbol_short!("YIELD"), buyer.clone(), token_id.clone());
+        env.storage().persistent().set(&position_key, position);
+        env.storage().persistent().extend_ttl(&position_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
+    }
+
+    // Helper function to get the scale of a token's yield accumulator, fixed when its share token was attached
+    fn yield_precision(env: &Env, token_id: &String) -> i128 {
+        env.storage().instance().get(&(symbol_short!("YIELDPRE"), token_id.clone())).unwrap_or(YIELD_PRECISION)
+    }
+
+    // Helper function to move yield accrued since the last settlement into a position's owed amount
+    fn settle_yield(env: &Env, buyer: &Address, token_id: &String) -> YieldPosition {
+        let accumulator: i128 = env.storage().instance().get(&(symbol_short!("YIELDACC"), token_id.clone())).unwrap_or(0);
+        let mut position = Self::read_yield_position(env, buyer, token_id);
+        let accrued = position.balance * accumulator / Self::yield_precision(env, token_id);
+        position.owed += accrued - position.reward_debt;
+        position.reward_debt = accrued;
+        position
+    }
+
+    // Helper function to change a holder's share balance, settling the yield accrued on the old one first
+    fn add_yield_balance(env: &Env, buyer: &Address, token_id: &String, amount: i128) {
+        let accumulator: i128 = env.storage().instance().get(&(symbol_short!("YIELDACC"), token_id.clone())).unwrap_or(0);
+        let mut position = Self::settle_yield(env, buyer, token_id);
+        position.balance += amount;
+        position.reward_debt = position.balance * accumulator / Self::yield_precision(env, token_id);
+        Self::write_yield_position(env, buyer, token_id, &position);
+    }
+
+    // Helper function to credit a referral fee to a widget payout address
+    fn accrue_referral_fee(env: &Env, payout: &Address, amount: i128) {
+        let balance_key = (symbol_short!("REFERRAL"), payout.clone());
+        let balance: i128 = env.storage().persistent().get(&balance_key).unwrap_or(0);
+        env.storage().persistent().set(&balance_key, &(balance + amount));
+        env.storage().persistent().extend_ttl(&balance_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
+    }
+
+    // Helper function to store a widget
+    fn write_widget(env: &Env, widget_hash: &String, widget: &Widget) {
+        let widget_key = (symbol_short!("WIDGET"), widget_hash.clone());
+        env.storage().persistent().set(&widget_key, widget);
+        env.storage().persistent().extend_ttl(&widget_key, INVESTMENT_LIFETIME_THRESHOLD, INVESTMENT_BUMP_AMOUNT);
+    }
+
+    // Helper function to keep the contract instance and its counters alive
+    fn extend_instance(env: &Env) {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+    }
+
+    // Helper function to get next investment ID
+    fn get_next_investment_id(env: Env) -> Result<u32, ContractError> {
+        let counter_key = symbol_short!("CNT");
+        Ok(env.storage().instance().get(&counter_key).unwrap_or(1))
+    }
+
+    // Get contract statistics
+    pub fn get_stats(env: Env) -> Result<Vec<i128>, ContractError> {
+        Self::require_initialized(&env)?;
+
+        let next_id = Self::get_next_investment_id(env.clone())?;
+        let total_investments = i128::from(next_id - 1);
+        let total_amount: i128 = env.storage().instance().get(&symbol_short!("TOTAMT")).unwrap_or(0);
+        let completed_investments: i128 = env.storage().instance().get(&symbol_short!("DONECNT")).unwrap_or(0);
         
         Ok(vec![&env, total_investments, total_amount, completed_investments])
     }
@@ -1,161 +1,3063 @@
 #![cfg(test)]
 
 use super::*;
-use soroban_sdk::{Address, Env, String, symbol_short};
+extern crate std;
+use soroban_sdk::testutils::storage::Persistent as _;
+use soroban_sdk::testutils::{Address as _, Events, Ledger, MockAuth, MockAuthInvoke};
+use soroban_sdk::token::{StellarAssetClient, TokenClient};
+use soroban_sdk::{symbol_short, vec, Address, Env, IntoVal, Map, String};
+use dob_mock_compliance::{MockCompliance, MockComplianceClient};
+use dob_share_token::{ShareToken, ShareTokenClient as ShareTokenAdminClient};
+
+// Register a Stellar asset contract to act as the USDC payment token
+fn create_payment_token<'a>(env: &Env, admin: &Address) -> (TokenClient<'a>, StellarAssetClient<'a>) {
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    (
+        TokenClient::new(env, &sac.address()),
+        StellarAssetClient::new(env, &sac.address()),
+    )
+}
 
 #[test]
 fn test_contract_initialization() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, DobLinkContract);
+    let contract_id = env.register(DobLinkContract, ());
     let client = DobLinkContractClient::new(&env, &contract_id);
-    
+
     // Create a test admin address
     let admin = Address::generate(&env);
-    
+    let (usdc, _) = create_payment_token(&env, &admin);
+
     // Initialize the contract
-    client.init(&admin);
-    
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
     // Test that admin is set correctly
     let retrieved_admin = client.get_admin();
     assert_eq!(retrieved_admin, admin);
+    assert_eq!(client.get_payment_token(), usdc.address);
+}
+
+#[test]
+fn test_init_only_once() {
+    let env = Env::default();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let attacker = Address::generate(&env);
+    let (usdc, _) = create_payment_token(&env, &admin);
+    let (fake_usdc, _) = create_payment_token(&env, &attacker);
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let token = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    let result = client.try_init(&attacker, &fake_usdc.address, &token);
+    assert_eq!(result, Err(Ok(ContractError::AlreadyInitialized)));
+
+    // The original configuration is untouched
+    assert_eq!(client.get_admin(), admin);
+    assert_eq!(client.get_payment_token(), usdc.address);
+    assert_eq!(client.list_tokens().len(), 1);
+}
+
+#[test]
+fn test_init_with_token_configuration() {
+    let env = Env::default();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let (usdc, _) = create_payment_token(&env, &admin);
+
+    // Invalid limits are rejected and leave the contract uninitialized
+    let invalid = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 0, 5000000000);
+    assert_eq!(
+        client.try_init(&admin, &usdc.address, &invalid),
+        Err(Ok(ContractError::InvalidTokenConfig))
+    );
+    assert_eq!(client.try_get_admin(), Err(Ok(ContractError::NotInitialized)));
+
+    let solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    client.init(&admin, &usdc.address, &solar);
+    assert_eq!(client.list_tokens(), vec![&env, TokenView { token: solar, raised: 0, fill_bps: 0 }]);
+}
+
+#[test]
+fn test_entrypoints_require_init() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let buyer = Address::generate(&env);
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    let not_initialized = Some(Ok(ContractError::NotInitialized));
+
+    assert_eq!(client.try_get_admin().err(), not_initialized);
+    assert_eq!(client.try_get_payment_token().err(), not_initialized);
+    assert_eq!(client.try_transfer_admin(&buyer).err(), not_initialized);
+    assert_eq!(client.try_accept_admin().err(), not_initialized);
+    assert_eq!(client.try_get_pending_admin().err(), not_initialized);
+    assert_eq!(client.try_register_token(&default_token(&env, &contract_id)).err(), not_initialized);
+    assert_eq!(client.try_delist_token(&token_id).err(), not_initialized);
+    assert_eq!(client.try_get_token_info(&token_id).err(), not_initialized);
+    assert_eq!(client.try_list_tokens().err(), not_initialized);
+    assert_eq!(client.try_create_investment(&buyer, &token_id, &50000000, &None).err(), not_initialized);
+    assert_eq!(client.try_get_investment(&1).err(), not_initialized);
+    assert_eq!(client.try_get_buyer_investments(&buyer, &0, &10).err(), not_initialized);
+    assert_eq!(client.try_get_buyer_investment_count(&buyer).err(), not_initialized);
+    assert_eq!(
+        client.try_update_investment_status(&buyer, &1, &InvestmentStatus::Completed).err(),
+        not_initialized
+    );
+    assert_eq!(client.try_get_token_total_investments(&token_id).err(), not_initialized);
+    assert_eq!(client.try_get_stats().err(), not_initialized);
+}
+
+#[test]
+fn test_non_admin_is_rejected() {
+    let env = Env::default();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let attacker = Address::generate(&env);
+    let (usdc, _) = create_payment_token(&env, &admin);
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+
+    // A non-admin signing for themselves cannot register a token
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &attacker,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "register_token",
+                args: (solar.clone(),).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_register_token(&solar);
+    assert!(result.is_err());
+
+    // Nor move an investment's status
+    let status = InvestmentStatus::Completed;
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &attacker,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "update_investment_status",
+                args: (attacker.clone(), 1u32, status).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_update_investment_status(&attacker, &1, &status);
+    assert!(result.is_err());
+
+    // Nor take over the admin role
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &attacker,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "transfer_admin",
+                args: (attacker.clone(),).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_transfer_admin(&attacker);
+    assert!(result.is_err());
+
+    // The admin's signature is accepted
+    client
+        .mock_auths(&[MockAuth {
+            address: &admin,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "register_token",
+                args: (solar.clone(),).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .register_token(&solar);
+    assert_eq!(client.get_token_info(&solar.id).token, solar);
+}
+
+#[test]
+fn test_admin_handover() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let new_admin = Address::generate(&env);
+    let (usdc, _) = create_payment_token(&env, &admin);
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    // Nothing to accept before a handover is proposed
+    assert_eq!(client.try_accept_admin(), Err(Ok(ContractError::NoPendingAdmin)));
+
+    client.transfer_admin(&new_admin);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("ADMPROP"),).into_val(&env),
+                AdminProposed {
+                    schema_version: EVENT_SCHEMA_VERSION,
+                    current_admin: admin.clone(),
+                    pending_admin: new_admin.clone(),
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(client.get_pending_admin(), Some(new_admin.clone()));
+    assert_eq!(client.get_admin(), admin);
+
+    client.accept_admin();
+    assert_eq!(env.auths()[0].0, new_admin);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("ADMIN"),).into_val(&env),
+                AdminChanged {
+                    schema_version: EVENT_SCHEMA_VERSION,
+                    previous_admin: admin.clone(),
+                    new_admin: new_admin.clone(),
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(client.get_admin(), new_admin);
+    assert_eq!(client.get_pending_admin(), None);
+
+    // The new admin is now the one who must sign
+    let solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    client.register_token(&solar);
+    assert_eq!(env.auths()[0].0, new_admin);
+}
+
+#[test]
+fn test_accept_admin_requires_pending_admin() {
+    let env = Env::default();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let new_admin = Address::generate(&env);
+    let (usdc, _) = create_payment_token(&env, &admin);
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    client
+        .mock_auths(&[MockAuth {
+            address: &admin,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "transfer_admin",
+                args: (new_admin.clone(),).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .transfer_admin(&new_admin);
+
+    // The current admin cannot accept on the new admin's behalf
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &admin,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "accept_admin",
+                args: ().into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_accept_admin();
+    assert!(result.is_err());
+    assert_eq!(client.get_admin(), admin);
 }
 
 #[test]
 fn test_token_info() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, DobLinkContract);
+    let contract_id = env.register(DobLinkContract, ());
     let client = DobLinkContractClient::new(&env, &contract_id);
-    
+
     let admin = Address::generate(&env);
-    client.init(&admin);
-    
+    let (usdc, _) = create_payment_token(&env, &admin);
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
     // Get default token info
-    let token_info = client.get_token_info();
-    assert_eq!(token_info.id, String::from_str(&env, "EVCHARGER001"));
-    assert_eq!(token_info.name, String::from_str(&env, "Electric Vehicle Charging Network"));
-    assert_eq!(token_info.apy, 1250); // 12.5%
-    assert_eq!(token_info.total_value_locked, 2400000000); // $2.4M
+    let token_info = client.get_token_info(&String::from_str(&env, "EVCHARGER001"));
+    assert_eq!(token_info.token.id, String::from_str(&env, "EVCHARGER001"));
+    assert_eq!(token_info.token.name, String::from_str(&env, "Electric Vehicle Charging Network"));
+    assert_eq!(token_info.token.apy, 1250); // 12.5%
+    assert_eq!(token_info.token.target_raise, 2400000000); // $2.4M
+    assert_eq!(token_info.raised, 0);
+    assert_eq!(token_info.fill_bps, 0);
+}
+
+// Register a share token whose mint authority is the DobLink contract
+fn create_share_token(env: &Env, contract_id: &Address, symbol: &str) -> Address {
+    let share_token_id = env.register(ShareToken, ());
+    ShareTokenAdminClient::new(env, &share_token_id).init(
+        contract_id,
+        &7,
+        &String::from_str(env, symbol),
+        &String::from_str(env, symbol),
+    );
+    share_token_id
+}
+
+// The EV charger offering used to initialize the contract
+fn default_token(env: &Env, contract_id: &Address) -> TokenInfo {
+    TokenInfo {
+        id: String::from_str(env, "EVCHARGER001"),
+        name: String::from_str(env, "Electric Vehicle Charging Network"),
+        apy: 1250, // 12.5%
+        target_raise: 2400000000, // $2.4M in micro units
+        allow_partial_fill: false,
+        min_investment: 10000000, // $10 in micro units
+        max_investment: 100000000000, // $100K in micro units
+        max_per_investor: 100000000000, // $100K in micro units
+        treasury: Address::generate(env),
+        cancel_window: 24 * 60 * 60, // 24h
+        share_token: create_share_token(env, contract_id, "EVC"),
+        share_price: 1000000, // $1 per share
+        sale_start: 0,
+        sale_end: 90 * 24 * 60 * 60, // Open for 90 days
+        soft_cap: 0,
+        require_kyc: false,
+        blocked_jurisdictions: vec![env],
+        compliance_contract: None,
+        referral_fee_bps: 0,
+    }
+}
+
+// Build a token offering with the given id and investment limits
+fn make_token(
+    env: &Env,
+    contract_id: &Address,
+    id: &str,
+    name: &str,
+    min_investment: i128,
+    max_investment: i128,
+) -> TokenInfo {
+    TokenInfo {
+        id: String::from_str(env, id),
+        name: String::from_str(env, name),
+        apy: 900, // 9%
+        target_raise: 1000000000, // $1K
+        allow_partial_fill: false,
+        min_investment,
+        max_investment,
+        max_per_investor: max_investment,
+        treasury: Address::generate(env),
+        cancel_window: 0,
+        share_token: create_share_token(env, contract_id, id),
+        share_price: 2000000, // $2 per share
+        sale_start: 0,
+        sale_end: 90 * 24 * 60 * 60, // Open for 90 days
+        soft_cap: 0,
+        require_kyc: false,
+        blocked_jurisdictions: vec![env],
+        compliance_contract: None,
+        referral_fee_bps: 0,
+    }
+}
+
+#[test]
+fn test_register_and_list_tokens() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let (usdc, _) = create_payment_token(&env, &admin);
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    let wind = make_token(&env, &contract_id, "WIND001", "Wind Park", 20000000, 10000000000);
+    client.register_token(&solar);
+    client.register_token(&wind);
+
+    let tokens = client.list_tokens();
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(tokens.get(0).unwrap().token.id, String::from_str(&env, "EVCHARGER001"));
+    assert_eq!(tokens.get(1).unwrap().token, solar);
+    assert_eq!(tokens.get(2).unwrap().token, wind);
+    assert_eq!(client.get_token_info(&wind.id).token, wind);
+
+    // Registering the same id twice is rejected
+    assert_eq!(client.try_register_token(&solar), Err(Ok(ContractError::TokenAlreadyExists)));
+
+    // Invalid limits are rejected
+    let invalid = make_token(&env, &contract_id, "ESTATE001", "Real Estate", 50000000, 10000000);
+    assert_eq!(client.try_register_token(&invalid), Err(Ok(ContractError::InvalidTokenConfig)));
+}
+
+#[test]
+fn test_update_token_info() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let (usdc, _) = create_payment_token(&env, &admin);
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    client.register_token(&solar);
+
+    let mut updated = solar.clone();
+    updated.name = String::from_str(&env, "Solar Farm II");
+    updated.apy = 1100;
+    updated.min_investment = 2000000;
+    client.update_token_info(&admin, &updated);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("TOKENUPD"), solar.id.clone()).into_val(&env),
+                TokenUpdated {
+                    schema_version: EVENT_SCHEMA_VERSION,
+                    token_id: solar.id.clone(),
+                    operator: admin.clone(),
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(client.get_token_info(&solar.id).token, updated);
+
+    // Other tokens are untouched
+    let ev_charger = client.get_token_info(&String::from_str(&env, "EVCHARGER001")).token;
+    assert_eq!(ev_charger.apy, 1250);
+
+    // Unknown tokens cannot be updated
+    let unknown = make_token(&env, &contract_id, "UNKNOWN", "Unknown", 1000000, 5000000000);
+    let result = client.try_update_token_info(&admin, &unknown);
+    assert_eq!(result, Err(Ok(ContractError::TokenNotFound)));
+
+    // Nor can limits be made invalid
+    updated.max_investment = 0;
+    let result = client.try_update_token_info(&admin, &updated);
+    assert_eq!(result, Err(Ok(ContractError::InvalidTokenConfig)));
+}
+
+#[test]
+fn test_register_token_checks_share_token() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let (usdc, _) = create_payment_token(&env, &admin);
+
+    // The first offering is checked as well
+    let mut ev_charger = default_token(&env, &contract_id);
+    ev_charger.share_token = Address::generate(&env);
+    assert_eq!(client.try_init(&admin, &usdc.address, &ev_charger), Err(Ok(ContractError::InvalidShareToken)));
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    // Shares minted by someone else could never be issued on completion
+    let mut solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    solar.share_token = create_share_token(&env, &admin, "SOL");
+    assert_eq!(client.try_register_token(&solar), Err(Ok(ContractError::InvalidShareToken)));
+
+    // Nor can an address without a share token contract be used
+    solar.share_token = Address::generate(&env);
+    assert_eq!(client.try_register_token(&solar), Err(Ok(ContractError::InvalidShareToken)));
+
+    // Decimals are bounded so share amounts cannot overflow
+    let share_token_id = env.register(ShareToken, ());
+    ShareTokenAdminClient::new(&env, &share_token_id).init(
+        &contract_id,
+        &19,
+        &String::from_str(&env, "SOL"),
+        &String::from_str(&env, "SOL"),
+    );
+    solar.share_token = share_token_id;
+    assert_eq!(client.try_register_token(&solar), Err(Ok(ContractError::InvalidShareToken)));
+
+    solar.share_token = create_share_token(&env, &contract_id, "SOL");
+    client.register_token(&solar);
+    assert_eq!(ShareTokenAdminClient::new(&env, &solar.share_token).transfer_hook(), Some(contract_id.clone()));
+
+    // One share token backs one offering
+    let mut wind = make_token(&env, &contract_id, "WIND001", "Wind Park", 1000000, 5000000000);
+    wind.share_token = solar.share_token.clone();
+    assert_eq!(client.try_register_token(&wind), Err(Ok(ContractError::InvalidShareToken)));
+
+    // And stays fixed once registered
+    solar.share_token = create_share_token(&env, &contract_id, "SOL");
+    assert_eq!(client.try_update_token_info(&admin, &solar), Err(Ok(ContractError::InvalidTokenConfig)));
+}
+
+#[test]
+fn test_delist_token() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    client.register_token(&solar);
+    client.delist_token(&solar.id);
+
+    assert_eq!(client.try_get_token_info(&solar.id), Err(Ok(ContractError::TokenNotFound)));
+    assert_eq!(client.list_tokens().len(), 1);
+
+    // Delisted tokens no longer accept investments
+    let result = client.try_create_investment(&buyer, &solar.id, &50000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::TokenNotFound)));
+    assert_eq!(client.try_delist_token(&solar.id), Err(Ok(ContractError::TokenNotFound)));
+}
+
+#[test]
+fn test_delist_token_with_escrow() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    client.register_token(&solar);
+    let investment_id = client.create_investment(&buyer, &solar.id, &50000000, &None);
+
+    // Pending funds would be stranded, so the token stays listed until they are settled
+    assert_eq!(client.try_delist_token(&solar.id), Err(Ok(ContractError::EscrowOutstanding)));
+
+    client.update_investment_status(&admin, &investment_id, &InvestmentStatus::Completed);
+    assert_eq!(client.get_escrow_balance(&solar.id), 0);
+    client.delist_token(&solar.id);
+
+    // The id keeps its investment history and cannot be registered again
+    let relisted = make_token(&env, &contract_id, "SOLAR001", "Solar Farm II", 1000000, 5000000000);
+    assert_eq!(client.try_register_token(&relisted), Err(Ok(ContractError::TokenAlreadyExists)));
+}
+
+#[test]
+fn test_investment_uses_referenced_token_limits() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    // Wind has a $20 minimum, unlike the $10 EV charger default
+    let wind = make_token(&env, &contract_id, "WIND001", "Wind Park", 20000000, 10000000000);
+    client.register_token(&wind);
+
+    let ev_charger_id = String::from_str(&env, "EVCHARGER001");
+    client.create_investment(&buyer, &ev_charger_id, &15000000, &None); // $15
+    let result = client.try_create_investment(&buyer, &wind.id, &15000000, &None); // $15
+    assert_eq!(result, Err(Ok(ContractError::BelowMinimum)));
+
+    let investment_id = client.create_investment(&buyer, &wind.id, &25000000, &None); // $25
+    assert_eq!(client.get_investment(&investment_id).token_id, wind.id);
+
+    // Unknown token ids are rejected
+    let unknown = String::from_str(&env, "UNKNOWN");
+    let result = client.try_create_investment(&buyer, &unknown, &25000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::TokenNotFound)));
+    assert_eq!(usdc.balance(&contract_id), 40000000);
 }
 
 #[test]
 fn test_create_investment() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, DobLinkContract);
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
     let client = DobLinkContractClient::new(&env, &contract_id);
-    
+
     let admin = Address::generate(&env);
     let buyer = Address::generate(&env);
-    
-    client.init(&admin);
-    
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
     // Create an investment
     let investment_amount = 50000000; // $50
     let token_id = String::from_str(&env, "EVCHARGER001");
-    
-    let investment_id = client.create_investment(&buyer, &token_id, &investment_amount);
+
+    let investment_id = client.create_investment(&buyer, &token_id, &investment_amount, &None);
     assert_eq!(investment_id, 1);
-    
+
     // Get the investment
     let investment = client.get_investment(&investment_id);
     assert_eq!(investment.buyer, buyer);
     assert_eq!(investment.token_id, token_id);
     assert_eq!(investment.amount, investment_amount);
-    assert_eq!(investment.status, String::from_str(&env, "pending"));
+    assert_eq!(investment.status, InvestmentStatus::Pending);
+}
+
+#[test]
+fn test_create_investment_transfers_payment() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    client.create_investment(&buyer, &token_id, &60000000, &None); // $60
+
+    // The buyer authorized the investment
+    let auths = env.auths();
+    assert_eq!(auths.len(), 1);
+    assert_eq!(auths[0].0, buyer);
+
+    // Funds moved from the buyer into the contract
+    assert_eq!(usdc.balance(&buyer), 40000000);
+    assert_eq!(usdc.balance(&contract_id), 60000000);
+}
+
+#[test]
+fn test_create_investment_insufficient_balance() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &20000000); // $20
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    let result = client.try_create_investment(&buyer, &token_id, &50000000, &None); // $50
+    assert!(result.is_err());
+
+    // Nothing was recorded and no funds moved
+    assert_eq!(client.try_get_investment(&1), Err(Ok(ContractError::InvestmentNotFound)));
+    assert_eq!(usdc.balance(&buyer), 20000000);
+    assert_eq!(usdc.balance(&contract_id), 0);
 }
 
 #[test]
 fn test_investment_validation() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, DobLinkContract);
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
     let client = DobLinkContractClient::new(&env, &contract_id);
-    
+
     let admin = Address::generate(&env);
     let buyer = Address::generate(&env);
-    
-    client.init(&admin);
-    
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &300000000000); // $300K
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
     let token_id = String::from_str(&env, "EVCHARGER001");
-    
+
     // Test minimum investment validation
     let too_small_amount = 5000000; // $5 (below $10 minimum)
-    let result = client.try_create_investment(&buyer, &token_id, &too_small_amount);
-    assert!(result.is_err());
-    
+    let result = client.try_create_investment(&buyer, &token_id, &too_small_amount, &None);
+    assert_eq!(result, Err(Ok(ContractError::BelowMinimum)));
+
     // Test maximum investment validation
     let too_large_amount = 200000000000; // $200K (above $100K maximum)
-    let result = client.try_create_investment(&buyer, &token_id, &too_large_amount);
-    assert!(result.is_err());
+    let result = client.try_create_investment(&buyer, &token_id, &too_large_amount, &None);
+    assert_eq!(result, Err(Ok(ContractError::AboveMaximum)));
 }
 
 #[test]
 fn test_get_buyer_investments() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, DobLinkContract);
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
     let client = DobLinkContractClient::new(&env, &contract_id);
-    
+
     let admin = Address::generate(&env);
     let buyer = Address::generate(&env);
-    
-    client.init(&admin);
-    
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &200000000); // $200
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
     let token_id = String::from_str(&env, "EVCHARGER001");
-    
+
     // Create multiple investments
-    client.create_investment(&buyer, &token_id, &50000000); // $50
-    client.create_investment(&buyer, &token_id, &75000000); // $75
-    
+    client.create_investment(&buyer, &token_id, &50000000, &None); // $50
+    client.create_investment(&buyer, &token_id, &75000000, &None); // $75
+
     // Get buyer investments
-    let investments = client.get_buyer_investments(&buyer);
-    assert_eq!(investments.len(), 2);
-    assert_eq!(investments.get(0).unwrap().amount, 50000000);
-    assert_eq!(investments.get(1).unwrap().amount, 75000000);
+    let page = client.get_buyer_investments(&buyer, &0, &10);
+    assert_eq!(page.ids.len(), 2);
+    assert_eq!(page.next_cursor, None);
+    assert_eq!(client.get_investment(&page.ids.get(0).unwrap()).amount, 50000000);
+    assert_eq!(client.get_investment(&page.ids.get(1).unwrap()).amount, 75000000);
+    assert_eq!(usdc.balance(&contract_id), 125000000);
+}
+
+#[test]
+fn test_get_buyer_investments_pagination() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let other_buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+    usdc_admin.mint(&other_buyer, &1000000000); // $1K
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    // Interleave two buyers so each index only holds its own ids
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    for _ in 0..5 {
+        client.create_investment(&buyer, &token_id, &10000000, &None);
+        client.create_investment(&other_buyer, &token_id, &10000000, &None);
+    }
+    assert_eq!(client.get_buyer_investment_count(&buyer), 5);
+
+    let page = client.get_buyer_investments(&buyer, &0, &2);
+    assert_eq!(page.ids, vec![&env, 1, 3]);
+    assert_eq!(page.next_cursor, Some(2));
+
+    let page = client.get_buyer_investments(&buyer, &2, &2);
+    assert_eq!(page.ids, vec![&env, 5, 7]);
+    assert_eq!(page.next_cursor, Some(4));
+
+    let page = client.get_buyer_investments(&buyer, &4, &2);
+    assert_eq!(page.ids, vec![&env, 9]);
+    assert_eq!(page.next_cursor, None);
+
+    // Cursors past the end return an empty last page
+    let page = client.get_buyer_investments(&buyer, &10, &2);
+    assert_eq!(page.ids.len(), 0);
+    assert_eq!(page.next_cursor, None);
+
+    let page = client.get_buyer_investments(&Address::generate(&env), &0, &2);
+    assert_eq!(page.ids.len(), 0);
+
+    // Page sizes are bounded
+    let result = client.try_get_buyer_investments(&buyer, &0, &0);
+    assert_eq!(result, Err(Ok(ContractError::InvalidPageSize)));
+    let result = client.try_get_buyer_investments(&buyer, &0, &(MAX_PAGE_SIZE + 1));
+    assert_eq!(result, Err(Ok(ContractError::InvalidPageSize)));
 }
 
 #[test]
 fn test_update_investment_status() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, DobLinkContract);
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
     let client = DobLinkContractClient::new(&env, &contract_id);
-    
+
     let admin = Address::generate(&env);
     let buyer = Address::generate(&env);
-    
-    client.init(&admin);
-    
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
     let token_id = String::from_str(&env, "EVCHARGER001");
-    let investment_id = client.create_investment(&buyer, &token_id, &50000000);
-    
+    let investment_id = client.create_investment(&buyer, &token_id, &50000000, &None);
+
     // Update status to completed
-    client.update_investment_status(&investment_id, &String::from_str(&env, "completed"));
-    
+    client.update_investment_status(&admin, &investment_id, &InvestmentStatus::Completed);
+
     let investment = client.get_investment(&investment_id);
-    assert_eq!(investment.status, String::from_str(&env, "completed"));
+    assert_eq!(investment.status, InvestmentStatus::Completed);
 }
 
 #[test]
 fn test_get_stats() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, DobLinkContract);
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
     let client = DobLinkContractClient::new(&env, &contract_id);
-    
+
     let admin = Address::generate(&env);
     let buyer1 = Address::generate(&env);
     let buyer2 = Address::generate(&env);
-    
-    client.init(&admin);
-    
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer1, &100000000); // $100
+    usdc_admin.mint(&buyer2, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
     let token_id = String::from_str(&env, "EVCHARGER001");
-    
+
     // Create investments
-    client.create_investment(&buyer1, &token_id, &50000000); // $50
-    client.create_investment(&buyer2, &token_id, &75000000); // $75
-    
+    client.create_investment(&buyer1, &token_id, &50000000, &None); // $50
+    client.create_investment(&buyer2, &token_id, &75000000, &None); // $75
+
     // Complete one investment
-    client.update_investment_status(&1, &String::from_str(&env, "completed"));
-    
+    client.update_investment_status(&admin, &1, &InvestmentStatus::Completed);
+
     let stats = client.get_stats();
     assert_eq!(stats.len(), 3);
     assert_eq!(stats.get(0).unwrap(), 2); // total investments
     assert_eq!(stats.get(1).unwrap(), 125000000); // total amount ($125)
     assert_eq!(stats.get(2).unwrap(), 1); // completed investments
 }
+
+#[test]
+fn test_investments_stored_as_persistent_entries() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    let investment_id = client.create_investment(&buyer, &token_id, &50000000, &None);
+
+    env.as_contract(&contract_id, || {
+        let investment_key = (symbol_short!("INV"), investment_id);
+        let investment: Investment = env.storage().persistent().get(&investment_key).unwrap();
+        assert_eq!(investment.amount, 50000000);
+        assert_eq!(env.storage().persistent().get_ttl(&investment_key), INVESTMENT_BUMP_AMOUNT);
+        assert!(!env.storage().instance().has(&symbol_short!("INV")));
+    });
+}
+
+#[test]
+fn test_token_total_investments() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+    let wind = make_token(&env, &contract_id, "WIND001", "Wind Park", 20000000, 10000000000);
+    client.register_token(&wind);
+
+    let ev_charger_id = String::from_str(&env, "EVCHARGER001");
+    let first = client.create_investment(&buyer, &ev_charger_id, &50000000, &None); // $50
+    let second = client.create_investment(&buyer, &ev_charger_id, &30000000, &None); // $30
+    let third = client.create_investment(&buyer, &wind.id, &40000000, &None); // $40
+    let fourth = client.create_investment(&buyer, &ev_charger_id, &20000000, &None); // $20
+
+    // Only completed investments count toward a token's total
+    assert_eq!(client.get_token_total_investments(&ev_charger_id), 0);
+    client.update_investment_status(&admin, &first, &InvestmentStatus::Completed);
+    client.update_investment_status(&admin, &second, &InvestmentStatus::Completed);
+    client.update_investment_status(&admin, &third, &InvestmentStatus::Completed);
+    client.update_investment_status(&admin, &fourth, &InvestmentStatus::Failed);
+
+    assert_eq!(client.get_token_total_investments(&ev_charger_id), 80000000);
+    assert_eq!(client.get_token_total_investments(&wind.id), 40000000);
+
+    let stats = client.get_stats();
+    assert_eq!(stats, vec![&env, 4, 140000000, 3]);
+}
+
+#[test]
+fn test_investment_status_transitions() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    let completed_id = client.create_investment(&buyer, &token_id, &20000000, &None);
+    let failed_id = client.create_investment(&buyer, &token_id, &20000000, &None);
+
+    // Pending cannot move back to pending
+    let result = client.try_update_investment_status(&admin, &completed_id, &InvestmentStatus::Pending);
+    assert_eq!(result, Err(Ok(ContractError::InvalidStatus)));
+
+    client.update_investment_status(&admin, &completed_id, &InvestmentStatus::Completed);
+    client.update_investment_status(&admin, &failed_id, &InvestmentStatus::Failed);
+
+    // Completed and failed investments are final
+    for status in [
+        InvestmentStatus::Pending,
+        InvestmentStatus::Failed,
+        InvestmentStatus::Refunded,
+        InvestmentStatus::Cancelled,
+    ] {
+        let result = client.try_update_investment_status(&admin, &completed_id, &status);
+        assert_eq!(result, Err(Ok(ContractError::InvalidStatus)));
+    }
+    let result = client.try_update_investment_status(&admin, &failed_id, &InvestmentStatus::Completed);
+    assert_eq!(result, Err(Ok(ContractError::InvalidStatus)));
+
+    assert_eq!(client.get_investment(&completed_id).status, InvestmentStatus::Completed);
+    assert_eq!(client.get_investment(&failed_id).status, InvestmentStatus::Failed);
+
+    let result = client.try_update_investment_status(&admin, &99, &InvestmentStatus::Completed);
+    assert_eq!(result, Err(Ok(ContractError::InvestmentNotFound)));
+}
+
+#[test]
+fn test_update_investment_status_emits_transition() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    let investment_id = client.create_investment(&buyer, &token_id, &50000000, &None);
+    client.update_investment_status(&admin, &investment_id, &InvestmentStatus::Cancelled);
+
+    // The refund transfer is emitted by the payment token, followed by the transition
+    let events = env.events().all();
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        vec![&env, events.last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("INVSTAT"), token_id.clone(), buyer.clone()).into_val(&env),
+                StatusChanged {
+                    schema_version: EVENT_SCHEMA_VERSION,
+                    investment_id,
+                    token_id: token_id.clone(),
+                    buyer: buyer.clone(),
+                    old_status: InvestmentStatus::Pending,
+                    new_status: InvestmentStatus::Cancelled,
+                    settlement: Settlement {
+                        refunded: 50000000,
+                        proceeds: 0,
+                        referral_fee: 0,
+                        referral_payout: None,
+                        platform_fee: 0,
+                    },
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_error_codes_are_stable() {
+    assert_eq!(ContractError::AlreadyInitialized as u32, 1);
+    assert_eq!(ContractError::NotInitialized as u32, 2);
+    assert_eq!(ContractError::Unauthorized as u32, 3);
+    assert_eq!(ContractError::BelowMinimum as u32, 4);
+    assert_eq!(ContractError::AboveMaximum as u32, 5);
+    assert_eq!(ContractError::TokenNotFound as u32, 6);
+    assert_eq!(ContractError::TokenAlreadyExists as u32, 7);
+    assert_eq!(ContractError::InvalidTokenConfig as u32, 8);
+    assert_eq!(ContractError::InvestmentNotFound as u32, 9);
+    assert_eq!(ContractError::InvalidStatus as u32, 10);
+    assert_eq!(ContractError::Paused as u32, 11);
+    assert_eq!(ContractError::NoPendingAdmin as u32, 12);
+    assert_eq!(ContractError::InvalidPageSize as u32, 13);
+    assert_eq!(ContractError::RefundNotAvailable as u32, 14);
+    assert_eq!(ContractError::CancellationWindowClosed as u32, 15);
+    assert_eq!(ContractError::RaiseCapExceeded as u32, 16);
+    assert_eq!(ContractError::SaleNotOpen as u32, 17);
+    assert_eq!(ContractError::SaleNotEnded as u32, 18);
+    assert_eq!(ContractError::InvestorLimitExceeded as u32, 19);
+    assert_eq!(ContractError::NotVerified as u32, 20);
+    assert_eq!(ContractError::KycExpired as u32, 21);
+    assert_eq!(ContractError::JurisdictionBlocked as u32, 22);
+    assert_eq!(ContractError::NotEligible as u32, 23);
+    assert_eq!(ContractError::NoYieldHolders as u32, 24);
+    assert_eq!(ContractError::WidgetAlreadyExists as u32, 25);
+    assert_eq!(ContractError::WidgetNotFound as u32, 26);
+    assert_eq!(ContractError::WidgetInactive as u32, 27);
+    assert_eq!(ContractError::WidgetTokenMismatch as u32, 28);
+    assert_eq!(ContractError::InvalidFeeConfig as u32, 29);
+    assert_eq!(ContractError::InsufficientFees as u32, 30);
+    assert_eq!(ContractError::EscrowOutstanding as u32, 31);
+    assert_eq!(ContractError::InvalidShareToken as u32, 32);
+    assert_eq!(ContractError::LegacyConfigRequired as u32, 33);
+    assert_eq!(ContractError::SoftCapNotMet as u32, 34);
+}
+
+#[test]
+fn test_escrow_released_to_treasury_on_completion() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    let treasury = client.get_token_info(&token_id).token.treasury;
+    let investment_id = client.create_investment(&buyer, &token_id, &50000000, &None);
+
+    // Funds sit in escrow while the investment is pending
+    assert_eq!(client.get_escrow_balance(&token_id), 50000000);
+    assert_eq!(usdc.balance(&contract_id), 50000000);
+    assert_eq!(usdc.balance(&treasury), 0);
+
+    client.update_investment_status(&admin, &investment_id, &InvestmentStatus::Completed);
+    assert_eq!(client.get_escrow_balance(&token_id), 0);
+    assert_eq!(usdc.balance(&contract_id), 0);
+    assert_eq!(usdc.balance(&treasury), 50000000);
+    assert_eq!(usdc.balance(&buyer), 50000000);
+}
+
+#[test]
+fn test_escrow_refunded_on_failure_and_cancellation() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    let treasury = client.get_token_info(&token_id).token.treasury;
+    let failed_id = client.create_investment(&buyer, &token_id, &50000000, &None);
+    let cancelled_id = client.create_investment(&buyer, &token_id, &30000000, &None);
+    assert_eq!(usdc.balance(&buyer), 20000000);
+
+    client.update_investment_status(&admin, &failed_id, &InvestmentStatus::Failed);
+    assert_eq!(usdc.balance(&buyer), 70000000);
+    assert_eq!(client.get_escrow_balance(&token_id), 30000000);
+
+    client.update_investment_status(&admin, &cancelled_id, &InvestmentStatus::Cancelled);
+    assert_eq!(usdc.balance(&buyer), 100000000);
+    assert_eq!(client.get_escrow_balance(&token_id), 0);
+    assert_eq!(usdc.balance(&contract_id), 0);
+    assert_eq!(usdc.balance(&treasury), 0);
+}
+
+#[test]
+fn test_claim_refund() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let other_buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    let investment_id = client.create_investment(&buyer, &token_id, &50000000, &None);
+
+    // Too early, the admin may still act
+    let result = client.try_claim_refund(&buyer, &investment_id);
+    assert_eq!(result, Err(Ok(ContractError::RefundNotAvailable)));
+
+    env.ledger().with_mut(|li| li.timestamp += REFUND_DELAY);
+
+    // Only the buyer can pull their own refund
+    let result = client.try_claim_refund(&other_buyer, &investment_id);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+
+    client.claim_refund(&buyer, &investment_id);
+    assert_eq!(env.auths()[0].0, buyer);
+    assert_eq!(client.get_investment(&investment_id).status, InvestmentStatus::Refunded);
+    assert_eq!(usdc.balance(&buyer), 100000000);
+    assert_eq!(client.get_escrow_balance(&token_id), 0);
+
+    // A refund can only be claimed once
+    let result = client.try_claim_refund(&buyer, &investment_id);
+    assert_eq!(result, Err(Ok(ContractError::RefundNotAvailable)));
+}
+
+#[test]
+fn test_claim_refund_after_completion_is_rejected() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    let investment_id = client.create_investment(&buyer, &token_id, &50000000, &None);
+    client.update_investment_status(&admin, &investment_id, &InvestmentStatus::Completed);
+
+    env.ledger().with_mut(|li| li.timestamp += REFUND_DELAY);
+    let result = client.try_claim_refund(&buyer, &investment_id);
+    assert_eq!(result, Err(Ok(ContractError::RefundNotAvailable)));
+    assert_eq!(usdc.balance(&buyer), 50000000);
+}
+
+#[test]
+fn test_cancel_investment_within_window() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let other_buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    let investment_id = client.create_investment(&buyer, &token_id, &50000000, &None);
+
+    // Still inside the 24h window an hour before it closes
+    env.ledger().with_mut(|li| li.timestamp += 23 * 60 * 60);
+
+    let result = client.try_cancel_investment(&other_buyer, &investment_id);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+
+    client.cancel_investment(&buyer, &investment_id);
+    assert_eq!(env.auths()[0].0, buyer);
+    assert_eq!(client.get_investment(&investment_id).status, InvestmentStatus::Cancelled);
+    assert_eq!(usdc.balance(&buyer), 100000000);
+    assert_eq!(client.get_escrow_balance(&token_id), 0);
+
+    // Already cancelled
+    let result = client.try_cancel_investment(&buyer, &investment_id);
+    assert_eq!(result, Err(Ok(ContractError::InvalidStatus)));
+}
+
+#[test]
+fn test_cancel_investment_after_window() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    let investment_id = client.create_investment(&buyer, &token_id, &50000000, &None);
+
+    env.ledger().with_mut(|li| li.timestamp += 24 * 60 * 60);
+
+    let result = client.try_cancel_investment(&buyer, &investment_id);
+    assert_eq!(result, Err(Ok(ContractError::CancellationWindowClosed)));
+    assert_eq!(client.get_investment(&investment_id).status, InvestmentStatus::Pending);
+    assert_eq!(usdc.balance(&buyer), 50000000);
+}
+
+#[test]
+fn test_cancel_investment_disabled_for_token() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    // Offerings without a window never accept cancellations
+    let solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    client.register_token(&solar);
+    let investment_id = client.create_investment(&buyer, &solar.id, &50000000, &None);
+
+    let result = client.try_cancel_investment(&buyer, &investment_id);
+    assert_eq!(result, Err(Ok(ContractError::CancellationWindowClosed)));
+}
+
+#[test]
+fn test_cancel_window_is_bounded() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    // A window past the 30 day refund delay is rejected, so timestamp + window cannot overflow
+    let mut solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    solar.cancel_window = u64::MAX;
+    assert_eq!(client.try_register_token(&solar), Err(Ok(ContractError::InvalidTokenConfig)));
+    solar.cancel_window = 30 * 24 * 60 * 60 + 1;
+    assert_eq!(client.try_register_token(&solar), Err(Ok(ContractError::InvalidTokenConfig)));
+
+    solar.cancel_window = 30 * 24 * 60 * 60;
+    client.register_token(&solar);
+    let investment_id = client.create_investment(&buyer, &solar.id, &50000000, &None);
+
+    env.ledger().with_mut(|li| li.timestamp += 29 * 24 * 60 * 60);
+    client.cancel_investment(&buyer, &investment_id);
+    assert_eq!(client.get_investment(&investment_id).status, InvestmentStatus::Cancelled);
+}
+
+#[test]
+fn test_completion_mints_shares() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    let share_token = TokenClient::new(&env, &client.get_token_info(&token_id).token.share_token);
+    let completed_id = client.create_investment(&buyer, &token_id, &50000000, &None); // $50
+    let failed_id = client.create_investment(&buyer, &token_id, &25000000, &None); // $25
+
+    // No shares while pending
+    assert_eq!(share_token.balance(&buyer), 0);
+
+    // $50 at $1 per share is 50 shares with 7 decimals
+    client.update_investment_status(&admin, &completed_id, &InvestmentStatus::Completed);
+    assert_eq!(share_token.balance(&buyer), 500000000);
+    assert_eq!(client.get_investment(&completed_id).shares, 500000000);
+
+    // Failed investments mint nothing
+    client.update_investment_status(&admin, &failed_id, &InvestmentStatus::Failed);
+    assert_eq!(share_token.balance(&buyer), 500000000);
+    assert_eq!(client.get_investment(&failed_id).shares, 0);
+    assert_eq!(ShareTokenAdminClient::new(&env, &share_token.address).total_supply(), 500000000);
+
+    // Shares are transferable SEP-41 balances
+    let friend = Address::generate(&env);
+    share_token.transfer(&buyer, &friend, &100000000);
+    assert_eq!(share_token.balance(&friend), 100000000);
+}
+
+#[test]
+fn test_completion_mints_at_token_price() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &100000000); // $100
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    // Solar shares cost $2, so each token gets its own share contract and price
+    let solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    client.register_token(&solar);
+    let investment_id = client.create_investment(&buyer, &solar.id, &30000000, &None); // $30
+    client.update_investment_status(&admin, &investment_id, &InvestmentStatus::Completed);
+
+    assert_eq!(TokenClient::new(&env, &solar.share_token).balance(&buyer), 150000000);
+    let ev_charger = client.get_token_info(&String::from_str(&env, "EVCHARGER001")).token;
+    assert_eq!(TokenClient::new(&env, &ev_charger.share_token).balance(&buyer), 0);
+
+    // Shares need a positive price
+    let mut free = make_token(&env, &contract_id, "FREE001", "Free", 1000000, 5000000000);
+    free.share_price = 0;
+    assert_eq!(client.try_register_token(&free), Err(Ok(ContractError::InvalidTokenConfig)));
+}
+
+#[test]
+fn test_raise_cap_enforced() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &2000000000); // $2K
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    // $1K cap
+    let solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    client.register_token(&solar);
+
+    client.create_investment(&buyer, &solar.id, &600000000, &None); // $600
+    let token_info = client.get_token_info(&solar.id);
+    assert_eq!(token_info.raised, 600000000);
+    assert_eq!(token_info.fill_bps, 6000); // 60%
+
+    // $500 would overshoot the remaining $400
+    let result = client.try_create_investment(&buyer, &solar.id, &500000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::RaiseCapExceeded)));
+    assert_eq!(usdc.balance(&buyer), 1400000000);
+
+    client.create_investment(&buyer, &solar.id, &400000000, &None); // $400
+    let token_info = client.get_token_info(&solar.id);
+    assert_eq!(token_info.raised, 1000000000);
+    assert_eq!(token_info.fill_bps, 10000); // 100%
+
+    let result = client.try_create_investment(&buyer, &solar.id, &1000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::RaiseCapExceeded)));
+}
+
+#[test]
+fn test_raise_cap_partial_fill() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &2000000000); // $2K
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let mut wind = make_token(&env, &contract_id, "WIND001", "Wind Park", 1000000, 5000000000);
+    wind.allow_partial_fill = true;
+    client.register_token(&wind);
+
+    client.create_investment(&buyer, &wind.id, &700000000, &None); // $700
+
+    // Only the remaining $300 of a $500 investment is taken
+    let investment_id = client.create_investment(&buyer, &wind.id, &500000000, &None);
+    assert_eq!(client.get_investment(&investment_id).amount, 300000000);
+    assert_eq!(usdc.balance(&buyer), 1000000000);
+    assert_eq!(client.get_token_info(&wind.id).fill_bps, 10000);
+
+    // Nothing left to fill
+    let result = client.try_create_investment(&buyer, &wind.id, &500000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::RaiseCapExceeded)));
+
+    // A remainder below the minimum investment is not filled either
+    let mut tidal = make_token(&env, &contract_id, "TIDAL001", "Tidal Array", 50000000, 5000000000); // $50 minimum
+    tidal.allow_partial_fill = true;
+    client.register_token(&tidal);
+    client.create_investment(&buyer, &tidal.id, &980000000, &None); // $980
+    let result = client.try_create_investment(&buyer, &tidal.id, &50000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::RaiseCapExceeded)));
+    assert_eq!(client.get_token_info(&tidal.id).raised, 980000000);
+}
+
+#[test]
+fn test_refunds_release_raise_capacity() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &2000000000); // $2K
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    client.register_token(&solar);
+
+    let completed_id = client.create_investment(&buyer, &solar.id, &600000000, &None); // $600
+    let failed_id = client.create_investment(&buyer, &solar.id, &400000000, &None); // $400
+    client.update_investment_status(&admin, &completed_id, &InvestmentStatus::Completed);
+    client.update_investment_status(&admin, &failed_id, &InvestmentStatus::Failed);
+
+    // Completed money stays raised, failed money frees up room
+    let token_info = client.get_token_info(&solar.id);
+    assert_eq!(token_info.raised, 600000000);
+    assert_eq!(token_info.fill_bps, 6000);
+    client.create_investment(&buyer, &solar.id, &400000000, &None);
+
+    // The cap cannot be lowered below what has been raised
+    let mut lowered = client.get_token_info(&solar.id).token;
+    lowered.target_raise = 500000000;
+    assert_eq!(client.try_update_token_info(&admin, &lowered), Err(Ok(ContractError::InvalidTokenConfig)));
+}
+
+#[test]
+fn test_investments_only_accepted_during_sale() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let mut solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    solar.sale_start = 1000;
+    solar.sale_end = 2000;
+    client.register_token(&solar);
+
+    let result = client.try_create_investment(&buyer, &solar.id, &100000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::SaleNotOpen)));
+
+    env.ledger().with_mut(|li| li.timestamp = 1000);
+    client.create_investment(&buyer, &solar.id, &100000000, &None);
+
+    env.ledger().with_mut(|li| li.timestamp = 2000);
+    let result = client.try_create_investment(&buyer, &solar.id, &100000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::SaleNotOpen)));
+    assert_eq!(usdc.balance(&buyer), 900000000);
+
+    // The window must be non-empty and the soft cap within the hard cap
+    let mut wind = make_token(&env, &contract_id, "WIND001", "Wind Park", 1000000, 5000000000);
+    wind.sale_end = wind.sale_start;
+    assert_eq!(client.try_register_token(&wind), Err(Ok(ContractError::InvalidTokenConfig)));
+    let mut wind = make_token(&env, &contract_id, "WIND001", "Wind Park", 1000000, 5000000000);
+    wind.soft_cap = wind.target_raise + 1;
+    assert_eq!(client.try_register_token(&wind), Err(Ok(ContractError::InvalidTokenConfig)));
+}
+
+#[test]
+fn test_finalize_offering_soft_cap_met() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let other_buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+    usdc_admin.mint(&other_buyer, &1000000000); // $1K
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let mut solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    solar.soft_cap = 500000000; // $500
+    client.register_token(&solar);
+
+    let first_id = client.create_investment(&buyer, &solar.id, &300000000, &None);
+    let cancelled_id = client.create_investment(&buyer, &solar.id, &100000000, &None);
+    let second_id = client.create_investment(&other_buyer, &solar.id, &300000000, &None);
+    client.update_investment_status(&admin, &cancelled_id, &InvestmentStatus::Failed);
+
+    let result = client.try_finalize_offering(&solar.id);
+    assert_eq!(result, Err(Ok(ContractError::SaleNotEnded)));
+    assert_eq!(client.get_finalization(&solar.id), None);
+
+    env.ledger().with_mut(|li| li.timestamp = solar.sale_end);
+    let finalization = client.finalize_offering(&solar.id);
+    assert_eq!(finalization, Finalization { soft_cap_met: true, cursor: 3, done: true });
+
+    let event = env.events().all().last().unwrap();
+    assert_eq!(
+        vec![&env, event],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("FINALIZED"), solar.id.clone()).into_val(&env),
+                OfferingFinalized { schema_version: EVENT_SCHEMA_VERSION, token_id: solar.id.clone(), soft_cap_met: true }.into_val(&env),
+            ),
+        ]
+    );
+
+    // Settled investments are left alone, pending ones complete
+    assert_eq!(client.get_investment(&first_id).status, InvestmentStatus::Completed);
+    assert_eq!(client.get_investment(&cancelled_id).status, InvestmentStatus::Failed);
+    assert_eq!(client.get_investment(&second_id).status, InvestmentStatus::Completed);
+    assert_eq!(usdc.balance(&solar.treasury), 600000000);
+    assert_eq!(client.get_escrow_balance(&solar.id), 0);
+
+    // Finalizing again is a no-op
+    assert_eq!(client.finalize_offering(&solar.id), finalization);
+}
+
+#[test]
+fn test_finalize_offering_soft_cap_missed_in_batches() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let mut solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    solar.soft_cap = 500000000; // $500
+    client.register_token(&solar);
+
+    for _ in 0..6 {
+        client.create_investment(&buyer, &solar.id, &10000000, &None); // $10
+    }
+    assert_eq!(usdc.balance(&buyer), 940000000);
+
+    env.ledger().with_mut(|li| li.timestamp = solar.sale_end);
+    let finalization = client.finalize_offering(&solar.id);
+    assert_eq!(finalization, Finalization { soft_cap_met: false, cursor: 4, done: false });
+    assert_eq!(client.get_investment(&4).status, InvestmentStatus::Failed);
+    assert_eq!(client.get_investment(&5).status, InvestmentStatus::Pending);
+
+    let finalization = client.finalize_offering(&solar.id);
+    assert_eq!(finalization, Finalization { soft_cap_met: false, cursor: 6, done: true });
+    assert_eq!(client.get_finalization(&solar.id), Some(finalization));
+    assert_eq!(client.get_investment(&6).status, InvestmentStatus::Failed);
+    assert_eq!(usdc.balance(&buyer), 1000000000);
+    assert_eq!(client.get_escrow_balance(&solar.id), 0);
+    assert_eq!(client.get_token_info(&solar.id).raised, 0);
+}
+
+#[test]
+fn test_no_cancellation_or_refund_once_sale_closes() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let mut solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    solar.soft_cap = 50000000; // $50
+    solar.cancel_window = 24 * 60 * 60;
+    client.register_token(&solar);
+
+    for _ in 0..6 {
+        client.create_investment(&buyer, &solar.id, &10000000, &None); // $10
+    }
+    env.ledger().with_mut(|li| li.timestamp = solar.sale_end - 60 * 60);
+    let late_id = client.create_investment(&buyer, &solar.id, &10000000, &None);
+
+    // Still inside its cancel window, but the sale has closed
+    env.ledger().with_mut(|li| li.timestamp = solar.sale_end);
+    let result = client.try_cancel_investment(&buyer, &late_id);
+    assert_eq!(result, Err(Ok(ContractError::CancellationWindowClosed)));
+
+    let finalization = client.finalize_offering(&solar.id);
+    assert_eq!(finalization, Finalization { soft_cap_met: true, cursor: 4, done: false });
+
+    // The sale window and soft cap are frozen, so the sale cannot be reopened
+    let mut extended = client.get_token_info(&solar.id).token;
+    extended.sale_end += 30 * 24 * 60 * 60;
+    assert_eq!(client.try_update_token_info(&admin, &extended), Err(Ok(ContractError::InvalidTokenConfig)));
+    let mut lowered = client.get_token_info(&solar.id).token;
+    lowered.soft_cap = 0;
+    assert_eq!(client.try_update_token_info(&admin, &lowered), Err(Ok(ContractError::InvalidTokenConfig)));
+    let result = client.try_create_investment(&buyer, &solar.id, &10000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::SaleNotOpen)));
+
+    // Nor are cancellations or refunds reopened mid-finalization
+    let result = client.try_cancel_investment(&buyer, &late_id);
+    assert_eq!(result, Err(Ok(ContractError::CancellationWindowClosed)));
+    env.ledger().with_mut(|li| li.timestamp = solar.sale_end + 30 * 24 * 60 * 60);
+    let result = client.try_claim_refund(&buyer, &5);
+    assert_eq!(result, Err(Ok(ContractError::RefundNotAvailable)));
+
+    let finalization = client.finalize_offering(&solar.id);
+    assert_eq!(finalization, Finalization { soft_cap_met: true, cursor: 7, done: true });
+    assert_eq!(client.get_investment(&late_id).status, InvestmentStatus::Completed);
+    assert_eq!(usdc.balance(&solar.treasury), 70000000);
+}
+
+#[test]
+fn test_soft_capped_investments_complete_only_after_finalization() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let mut solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    solar.soft_cap = 50000000; // $50
+    client.register_token(&solar);
+    let mut ids = std::vec::Vec::new();
+    for _ in 0..6 {
+        ids.push(client.create_investment(&buyer, &solar.id, &10000000, &None)); // $10
+    }
+
+    // Offerings without a soft cap complete as before
+    let token = client.get_token_info(&String::from_str(&env, "EVCHARGER001")).token;
+    let investment_id = client.create_investment(&buyer, &token.id, &100000000, &None);
+    client.update_investment_status(&admin, &investment_id, &InvestmentStatus::Completed);
+    assert_eq!(usdc.balance(&token.treasury), 100000000);
+
+    // Escrow stays put while the soft cap is undecided, during and after the sale
+    let result = client.try_update_investment_status(&admin, &ids[5], &InvestmentStatus::Completed);
+    assert_eq!(result, Err(Ok(ContractError::SoftCapNotMet)));
+    env.ledger().with_mut(|li| li.timestamp = solar.sale_end);
+    let result = client.try_update_investment_status(&admin, &ids[5], &InvestmentStatus::Completed);
+    assert_eq!(result, Err(Ok(ContractError::SoftCapNotMet)));
+    assert_eq!(usdc.balance(&solar.treasury), 0);
+
+    // Once finalization has found the cap met, the rest can be completed directly
+    assert_eq!(client.finalize_offering(&solar.id), Finalization { soft_cap_met: true, cursor: 4, done: false });
+    client.update_investment_status(&admin, &ids[5], &InvestmentStatus::Completed);
+    assert_eq!(usdc.balance(&solar.treasury), 50000000);
+    assert_eq!(client.finalize_offering(&solar.id), Finalization { soft_cap_met: true, cursor: 6, done: true });
+    assert_eq!(usdc.balance(&solar.treasury), 60000000);
+}
+
+#[test]
+fn test_soft_cap_missed_blocks_completion() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let mut solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    solar.soft_cap = 500000000; // $500
+    client.register_token(&solar);
+    let mut ids = std::vec::Vec::new();
+    for _ in 0..6 {
+        ids.push(client.create_investment(&buyer, &solar.id, &10000000, &None)); // $10
+    }
+
+    env.ledger().with_mut(|li| li.timestamp = solar.sale_end);
+    assert_eq!(client.finalize_offering(&solar.id), Finalization { soft_cap_met: false, cursor: 4, done: false });
+    let result = client.try_update_investment_status(&admin, &ids[5], &InvestmentStatus::Completed);
+    assert_eq!(result, Err(Ok(ContractError::SoftCapNotMet)));
+
+    assert_eq!(client.finalize_offering(&solar.id), Finalization { soft_cap_met: false, cursor: 6, done: true });
+    assert_eq!(usdc.balance(&buyer), 1000000000);
+    assert_eq!(usdc.balance(&solar.treasury), 0);
+}
+
+#[test]
+fn test_finalize_batch_fits_transaction_limits() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+    client.set_fee_config(&100, &Address::generate(&env));
+    env.ledger().with_mut(|li| li.timestamp += 7 * 24 * 60 * 60);
+
+    // Worst case: every completion has its own buyer and its own widget payout, and pays a platform fee
+    let mut solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    solar.referral_fee_bps = 200;
+    solar.sale_end = env.ledger().timestamp() + 24 * 60 * 60;
+    client.register_token(&solar);
+    for widget in ["w-01", "w-02", "w-03", "w-04", "w-05", "w-06", "w-07", "w-08"] {
+        let buyer = Address::generate(&env);
+        let site_owner = Address::generate(&env);
+        let widget_hash = String::from_str(&env, widget);
+        usdc_admin.mint(&buyer, &10000000);
+        client.register_widget(&admin, &site_owner, &widget_hash, &solar.id, &site_owner);
+        client.create_investment(&buyer, &solar.id, &10000000, &Some(widget_hash));
+    }
+
+    env.ledger().with_mut(|li| li.timestamp = solar.sale_end);
+    assert_eq!(client.finalize_offering(&solar.id), Finalization { soft_cap_met: true, cursor: 4, done: false });
+
+    // Stay under the per-transaction limits of 25 written and 40 accessed entries, leaving
+    // room for the contract code entries a deployed contract adds to the footprint
+    let resources = env.cost_estimate().resources();
+    assert!(resources.write_entries <= 20, "{resources:?}");
+    assert!(resources.read_entries + resources.write_entries <= 35, "{resources:?}");
+}
+
+#[test]
+fn test_max_per_investor() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let other_buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+    usdc_admin.mint(&other_buyer, &1000000000); // $1K
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+
+    let mut solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 200000000);
+    solar.max_per_investor = 300000000; // $300 across all calls
+    solar.cancel_window = 24 * 60 * 60;
+    client.register_token(&solar);
+
+    client.create_investment(&buyer, &solar.id, &200000000, &None);
+    let cancelled_id = client.create_investment(&buyer, &solar.id, &100000000, &None);
+    assert_eq!(client.get_investor_total(&buyer, &solar.id), 300000000);
+
+    // Each call is within max_investment but the total would not be
+    let result = client.try_create_investment(&buyer, &solar.id, &1000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::InvestorLimitExceeded)));
+
+    // The limit is per buyer
+    client.create_investment(&other_buyer, &solar.id, &200000000, &None);
+    assert_eq!(client.get_investor_total(&other_buyer, &solar.id), 200000000);
+
+    // Cancelling frees up the buyer's allowance again
+    client.cancel_investment(&buyer, &cancelled_id);
+    assert_eq!(client.get_investor_total(&buyer, &solar.id), 200000000);
+    client.create_investment(&buyer, &solar.id, &100000000, &None);
+    assert_eq!(client.get_investor_total(&buyer, &solar.id), 300000000);
+}
+
+#[test]
+fn test_pause_and_unpause() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    let token = default_token(&env, &contract_id);
+    client.init(&admin, &usdc.address, &token);
+    let investment_id = client.create_investment(&buyer, &token.id, &100000000, &None);
+
+    client.pause(&admin);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("PAUSED"), None::<String>).into_val(&env),
+                Paused { schema_version: EVENT_SCHEMA_VERSION, operator: admin.clone(), token_id: None, paused: true }.into_val(&env),
+            ),
+        ]
+    );
+    assert!(client.is_paused());
+    assert!(client.is_token_paused(&token.id));
+
+    let result = client.try_create_investment(&buyer, &token.id, &100000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::Paused)));
+    let result = client.try_update_investment_status(&admin, &investment_id, &InvestmentStatus::Completed);
+    assert_eq!(result, Err(Ok(ContractError::Paused)));
+    env.ledger().with_mut(|li| li.timestamp = token.sale_end);
+    assert_eq!(client.try_finalize_offering(&token.id), Err(Ok(ContractError::Paused)));
+
+    // Views keep working while paused
+    assert_eq!(client.get_investment(&investment_id).amount, 100000000);
+    assert_eq!(client.get_escrow_balance(&token.id), 100000000);
+
+    client.unpause(&admin);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("PAUSED"), None::<String>).into_val(&env),
+                Paused { schema_version: EVENT_SCHEMA_VERSION, operator: admin.clone(), token_id: None, paused: false }.into_val(&env),
+            ),
+        ]
+    );
+    assert!(!client.is_paused());
+    client.finalize_offering(&token.id);
+    assert_eq!(client.get_investment(&investment_id).status, InvestmentStatus::Completed);
+}
+
+#[test]
+fn test_pause_single_token() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    let token = default_token(&env, &contract_id);
+    client.init(&admin, &usdc.address, &token);
+    let solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    client.register_token(&solar);
+    let solar_id = client.create_investment(&buyer, &solar.id, &100000000, &None);
+
+    client.pause_token(&admin, &solar.id);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("PAUSED"), Some(solar.id.clone())).into_val(&env),
+                Paused {
+                    schema_version: EVENT_SCHEMA_VERSION,
+                    operator: admin.clone(),
+                    token_id: Some(solar.id.clone()),
+                    paused: true,
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert!(!client.is_paused());
+    assert!(client.is_token_paused(&solar.id));
+
+    // Only the paused offering is halted, and failing its investments still refunds buyers
+    let result = client.try_create_investment(&buyer, &solar.id, &100000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::Paused)));
+    let result = client.try_update_investment_status(&admin, &solar_id, &InvestmentStatus::Completed);
+    assert_eq!(result, Err(Ok(ContractError::Paused)));
+    client.update_investment_status(&admin, &solar_id, &InvestmentStatus::Failed);
+    assert_eq!(usdc.balance(&buyer), 1000000000);
+    client.create_investment(&buyer, &token.id, &100000000, &None);
+
+    client.unpause_token(&admin, &solar.id);
+    assert!(!client.is_token_paused(&solar.id));
+    client.create_investment(&buyer, &solar.id, &100000000, &None);
+
+    let unknown = String::from_str(&env, "UNKNOWN");
+    assert_eq!(client.try_pause_token(&admin, &unknown), Err(Ok(ContractError::TokenNotFound)));
+}
+
+#[test]
+fn test_only_admin_can_pause() {
+    let env = Env::default();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let attacker = Address::generate(&env);
+    let payment_token = Address::generate(&env);
+    env.mock_all_auths();
+    client.init(&admin, &payment_token, &default_token(&env, &contract_id));
+
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &attacker,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "pause",
+                args: (attacker.clone(),).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_pause(&attacker);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+
+    // Nor can they sign for the admin
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &attacker,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "pause",
+                args: (admin.clone(),).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_pause(&admin);
+    assert!(result.is_err());
+    assert!(!client.is_paused());
+}
+
+// Build this crate to wasm, once per test run, so upgrades always target the current source
+// Built by `make test-wasms` from this crate's source with the release and release-with-logs profiles
+const DOB_LINK_WASM: &[u8] = include_bytes!("../test_wasms/dob_link.wasm");
+const DOB_LINK_WITH_LOGS_WASM: &[u8] = include_bytes!("../test_wasms/dob_link_with_logs.wasm");
+
+#[test]
+fn test_init_writes_current_storage_version() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let payment_token = Address::generate(&env);
+    client.init(&admin, &payment_token, &default_token(&env, &contract_id));
+
+    assert_eq!(client.get_storage_version(), 2);
+    assert_eq!(client.migrate(&None), 2);
+}
+
+#[test]
+fn test_migrate_from_version_1() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let other_buyer = Address::generate(&env);
+    let (usdc, _) = create_payment_token(&env, &admin);
+
+    // Lay the storage out exactly as the version 1 init and create_investment left it
+    let legacy_token = LegacyTokenInfo {
+        id: String::from_str(&env, "EVCHARGER001"),
+        name: String::from_str(&env, "Electric Vehicle Charging Network"),
+        apy: 1250,
+        total_value_locked: 2400000000,
+        min_investment: 10000000,
+        max_investment: 100000000000,
+    };
+    let legacy_investment = |buyer: &Address, token_id: &str, amount: i128, status: &str| LegacyInvestment {
+        buyer: buyer.clone(),
+        token_id: String::from_str(&env, token_id),
+        amount,
+        timestamp: 1000,
+        status: String::from_str(&env, status),
+    };
+    env.as_contract(&contract_id, || {
+        let mut investments = Map::new(&env);
+        investments.set(1u32, legacy_investment(&buyer, "EVCHARGER001", 100000000, "completed"));
+        investments.set(2, legacy_investment(&buyer, "EVCHARGER001", 50000000, "pending"));
+        investments.set(3, legacy_investment(&other_buyer, "anything", 20000000, "completed"));
+        env.storage().instance().set(&symbol_short!("ADMIN"), &admin);
+        env.storage().instance().set(&symbol_short!("TOKEN"), &legacy_token);
+        env.storage().instance().set(&symbol_short!("INV"), &investments);
+        env.storage().instance().set(&symbol_short!("CNT"), &4u32);
+    });
+    assert_eq!(client.get_storage_version(), 1);
+    assert_eq!(client.try_get_admin(), Err(Ok(ContractError::NotInitialized)));
+
+    // Version 1 never recorded a payment asset or the newer offering settings
+    assert_eq!(client.try_migrate(&None), Err(Ok(ContractError::LegacyConfigRequired)));
+
+    let settings = make_token(&env, &contract_id, "IGNORED", "Ignored", 1, 100000000000);
+    let legacy_config = LegacyConfig { payment_token: usdc.address.clone(), token: settings.clone() };
+    assert_eq!(client.migrate(&Some(legacy_config.clone())), 1);
+    assert_eq!(client.get_storage_version(), 1);
+    assert_eq!(client.migrate(&None), 2);
+    assert_eq!(env.auths()[0].0, admin);
+    let event = env.events().all().last().unwrap();
+    assert_eq!(
+        vec![&env, event],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("MIGRATED"),).into_val(&env),
+                Migrated { schema_version: EVENT_SCHEMA_VERSION, from_storage_version: 1, to_storage_version: 2 }.into_val(&env),
+            ),
+        ]
+    );
+
+    // The contract now behaves as if it had been initialized by the current version
+    assert_eq!(client.get_storage_version(), 2);
+    assert_eq!(client.get_admin(), admin);
+    assert_eq!(client.get_payment_token(), usdc.address);
+    let token = TokenInfo {
+        id: legacy_token.id.clone(),
+        name: legacy_token.name.clone(),
+        apy: 1250,
+        target_raise: 2400000000,
+        min_investment: 10000000,
+        max_investment: 100000000000,
+        ..settings
+    };
+    assert_eq!(client.get_token_info(&token.id), TokenView { token: token.clone(), raised: 120000000, fill_bps: 500 });
+    assert_eq!(client.list_tokens().len(), 1);
+
+    assert_eq!(
+        client.get_investment(&1),
+        Investment {
+            buyer: buyer.clone(),
+            token_id: token.id.clone(),
+            amount: 100000000,
+            timestamp: 1000,
+            status: InvestmentStatus::Completed,
+            shares: 500000000, // $100 at $2 per share, 7 decimals
+            widget_hash: None,
+        }
+    );
+    assert_eq!(client.get_investment(&2).status, InvestmentStatus::Failed);
+    assert_eq!(client.get_investment(&3).token_id, token.id);
+
+    // Completed version 1 investments now hold the shares their yield is paid on
+    let share_token = TokenClient::new(&env, &token.share_token);
+    assert_eq!(share_token.balance(&buyer), 500000000);
+    assert_eq!(share_token.balance(&other_buyer), 100000000);
+    assert_eq!(client.get_buyer_investment_count(&buyer), 2);
+    assert_eq!(client.get_buyer_investment_count(&other_buyer), 1);
+    assert_eq!(client.get_investor_total(&buyer, &token.id), 100000000);
+    assert_eq!(client.get_token_total_investments(&token.id), 120000000);
+    assert_eq!(client.get_escrow_balance(&token.id), 0);
+    assert_eq!(client.get_stats(), vec![&env, 3, 170000000, 2]);
+    env.as_contract(&contract_id, || {
+        assert!(!env.storage().instance().has(&symbol_short!("INV")));
+        assert!(!env.storage().instance().has(&symbol_short!("TOKEN")));
+    });
+
+    // New investments continue the version 1 id sequence
+    StellarAssetClient::new(&env, &usdc.address).mint(&buyer, &10000000);
+    assert_eq!(client.create_investment(&buyer, &token.id, &10000000, &None), 4);
+
+    // Running it again leaves the migrated data alone
+    assert_eq!(client.migrate(&Some(legacy_config)), 2);
+    assert_eq!(client.get_buyer_investment_count(&buyer), 3);
+}
+
+#[test]
+fn test_migrate_batch_fits_transaction_limits() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let (usdc, _) = create_payment_token(&env, &admin);
+
+    // Worst case: every version 1 investment completed and has its own buyer
+    let legacy_token = LegacyTokenInfo {
+        id: String::from_str(&env, "EVCHARGER001"),
+        name: String::from_str(&env, "Electric Vehicle Charging Network"),
+        apy: 1250,
+        total_value_locked: 100000000000,
+        min_investment: 10000000,
+        max_investment: 100000000000,
+    };
+    let count = 2 * MIGRATE_BATCH_SIZE + 1;
+    env.as_contract(&contract_id, || {
+        let mut investments = Map::new(&env);
+        for investment_id in 1..=count {
+            let legacy = LegacyInvestment {
+                buyer: Address::generate(&env),
+                token_id: legacy_token.id.clone(),
+                amount: 10000000,
+                timestamp: 1000,
+                status: String::from_str(&env, "completed"),
+            };
+            investments.set(investment_id, legacy);
+        }
+        env.storage().instance().set(&symbol_short!("ADMIN"), &admin);
+        env.storage().instance().set(&symbol_short!("TOKEN"), &legacy_token);
+        env.storage().instance().set(&symbol_short!("INV"), &investments);
+        env.storage().instance().set(&symbol_short!("CNT"), &(count + 1));
+    });
+
+    let settings = make_token(&env, &contract_id, "IGNORED", "Ignored", 1, 100000000000);
+    let legacy_config = LegacyConfig { payment_token: usdc.address.clone(), token: settings };
+    // Stay under the same per-transaction limits as finalize_offering
+    let assert_fits_limits = || {
+        let resources = env.cost_estimate().resources();
+        assert!(resources.write_entries <= 20, "{resources:?}");
+        assert!(resources.read_entries + resources.write_entries <= 35, "{resources:?}");
+    };
+    assert_eq!(client.migrate(&Some(legacy_config)), 1);
+    assert_fits_limits();
+    assert_eq!(client.try_get_admin(), Err(Ok(ContractError::NotInitialized)));
+
+    // Later batches resume from the stored cursor without the legacy config
+    assert_eq!(client.migrate(&None), 1);
+    assert_fits_limits();
+
+    assert_eq!(client.migrate(&None), 2);
+    assert_eq!(client.get_stats(), vec![&env, i128::from(count), 10000000 * i128::from(count), i128::from(count)]);
+    assert_eq!(client.get_token_info(&legacy_token.id).raised, 10000000 * i128::from(count));
+    assert_eq!(client.get_investment(&count).shares, 50000000);
+}
+
+#[test]
+fn test_upgrade_keeps_storage() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DOB_LINK_WASM, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    let token = default_token(&env, &contract_id);
+    client.init(&admin, &usdc.address, &token);
+    let pending_id = client.create_investment(&buyer, &token.id, &100000000, &None);
+    let completed_id = client.create_investment(&buyer, &token.id, &200000000, &None);
+    client.update_investment_status(&admin, &completed_id, &InvestmentStatus::Completed);
+
+    let token_view = client.get_token_info(&token.id);
+    let investments = client.get_buyer_investments(&buyer, &0, &10);
+
+    let old_wasm_hash = env.deployer().upload_contract_wasm(DOB_LINK_WASM);
+    let new_wasm_hash = env.deployer().upload_contract_wasm(DOB_LINK_WITH_LOGS_WASM);
+    assert_ne!(old_wasm_hash, new_wasm_hash);
+    client.upgrade(&new_wasm_hash);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("UPGRADED"),).into_val(&env),
+                Upgraded { schema_version: EVENT_SCHEMA_VERSION, wasm_hash: new_wasm_hash.clone() }.into_val(&env),
+            ),
+        ]
+    );
+
+    // The second build serves the same address and data
+    assert_eq!(client.get_admin(), admin);
+    assert_eq!(client.get_storage_version(), 2);
+    assert_eq!(client.get_token_info(&token.id), token_view);
+    assert_eq!(client.get_token_info(&token.id).raised, 300000000);
+    assert_eq!(client.get_buyer_investments(&buyer, &0, &10), investments);
+    assert_eq!(client.get_investment(&pending_id).status, InvestmentStatus::Pending);
+    assert_eq!(client.get_escrow_balance(&token.id), 100000000);
+    assert_eq!(client.get_token_total_investments(&token.id), 200000000);
+    assert_eq!(client.get_stats(), vec![&env, 2, 300000000, 1]);
+    assert_eq!(usdc.balance(&contract_id), 100000000);
+
+    // And keeps working on it
+    client.update_investment_status(&admin, &pending_id, &InvestmentStatus::Completed);
+    assert_eq!(client.get_escrow_balance(&token.id), 0);
+    assert_eq!(usdc.balance(&token.treasury), 300000000);
+
+    // And still answers to the admin
+    assert_eq!(client.migrate(&None), 2);
+    assert_eq!(env.auths()[0].0, admin);
+}
+
+#[test]
+fn test_only_admin_can_upgrade() {
+    let env = Env::default();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let attacker = Address::generate(&env);
+    let payment_token = Address::generate(&env);
+    env.mock_all_auths();
+    client.init(&admin, &payment_token, &default_token(&env, &contract_id));
+
+    let new_wasm_hash = env.deployer().upload_contract_wasm(DOB_LINK_WASM);
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &attacker,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "upgrade",
+                args: (new_wasm_hash.clone(),).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_upgrade(&new_wasm_hash);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_grant_and_revoke_role() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let operator = Address::generate(&env);
+    let payment_token = Address::generate(&env);
+    let token = default_token(&env, &contract_id);
+    client.init(&admin, &payment_token, &token);
+
+    let solar_id = Some(String::from_str(&env, "SOLAR001"));
+    client.grant_role(&Role::TokenManager, &operator, &solar_id);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("ROLE"), operator.clone()).into_val(&env),
+                RoleChanged {
+                    schema_version: EVENT_SCHEMA_VERSION,
+                    role: Role::TokenManager,
+                    account: operator.clone(),
+                    token_id: solar_id.clone(),
+                    granted: true,
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+
+    // Each grant is its own persistent entry, kept alive like investment records
+    env.as_contract(&contract_id, || {
+        let role_key = (symbol_short!("ROLE"), Role::TokenManager, operator.clone(), solar_id.clone());
+        assert!(env.storage().persistent().has(&role_key));
+        assert_eq!(env.storage().persistent().get_ttl(&role_key), INVESTMENT_BUMP_AMOUNT);
+    });
+
+    // A per-token grant only covers that token
+    assert!(client.has_role(&Role::TokenManager, &operator, &solar_id));
+    assert!(!client.has_role(&Role::TokenManager, &operator, &Some(token.id.clone())));
+    assert!(!client.has_role(&Role::TokenManager, &operator, &None));
+    assert!(!client.has_role(&Role::Pauser, &operator, &solar_id));
+
+    // A global grant covers every token
+    client.grant_role(&Role::StatusUpdater, &operator, &None);
+    assert!(client.has_role(&Role::StatusUpdater, &operator, &solar_id));
+    assert!(client.has_role(&Role::StatusUpdater, &operator, &None));
+
+    client.revoke_role(&Role::TokenManager, &operator, &solar_id);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("ROLE"), operator.clone()).into_val(&env),
+                RoleChanged {
+                    schema_version: EVENT_SCHEMA_VERSION,
+                    role: Role::TokenManager,
+                    account: operator.clone(),
+                    token_id: solar_id.clone(),
+                    granted: false,
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert!(!client.has_role(&Role::TokenManager, &operator, &solar_id));
+}
+
+#[test]
+fn test_roles_gate_operations() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let operator = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    let token = default_token(&env, &contract_id);
+    client.init(&admin, &usdc.address, &token);
+    let solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    client.register_token(&solar);
+    let solar_investment = client.create_investment(&buyer, &solar.id, &100000000, &None);
+    let ev_investment = client.create_investment(&buyer, &token.id, &100000000, &None);
+
+    // Without a role the operator is turned away
+    let mut updated = solar.clone();
+    updated.apy = 1000;
+    assert_eq!(client.try_update_token_info(&operator, &updated), Err(Ok(ContractError::Unauthorized)));
+    let result = client.try_update_investment_status(&operator, &solar_investment, &InvestmentStatus::Completed);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+    assert_eq!(client.try_pause_token(&operator, &solar.id), Err(Ok(ContractError::Unauthorized)));
+
+    // A project operator manages their own offering only
+    let solar_id = Some(solar.id.clone());
+    client.grant_role(&Role::TokenManager, &operator, &solar_id);
+    client.grant_role(&Role::StatusUpdater, &operator, &solar_id);
+    client.grant_role(&Role::Pauser, &operator, &solar_id);
+
+    client.update_token_info(&operator, &updated);
+    assert_eq!(env.auths()[0].0, operator);
+    assert_eq!(client.get_token_info(&solar.id).token.apy, 1000);
+    client.update_investment_status(&operator, &solar_investment, &InvestmentStatus::Completed);
+    client.pause_token(&operator, &solar.id);
+    client.unpause_token(&operator, &solar.id);
+
+    let mut ev_updated = token.clone();
+    ev_updated.apy = 1000;
+    assert_eq!(client.try_update_token_info(&operator, &ev_updated), Err(Ok(ContractError::Unauthorized)));
+    let result = client.try_update_investment_status(&operator, &ev_investment, &InvestmentStatus::Completed);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+    assert_eq!(client.try_pause(&operator), Err(Ok(ContractError::Unauthorized)));
+
+    // Where proceeds go and who may invest need the matching role as well
+    let mut redirected = updated.clone();
+    redirected.treasury = operator.clone();
+    assert_eq!(client.try_update_token_info(&operator, &redirected), Err(Ok(ContractError::Unauthorized)));
+    let mut referral_raised = updated.clone();
+    referral_raised.referral_fee_bps = 500;
+    assert_eq!(client.try_update_token_info(&operator, &referral_raised), Err(Ok(ContractError::Unauthorized)));
+    let mut unrestricted = updated.clone();
+    unrestricted.compliance_contract = Some(operator.clone());
+    assert_eq!(client.try_update_token_info(&operator, &unrestricted), Err(Ok(ContractError::Unauthorized)));
+    let mut kyc_required = updated.clone();
+    kyc_required.require_kyc = true;
+    kyc_required.compliance_contract = None;
+    assert_eq!(client.try_update_token_info(&operator, &kyc_required), Err(Ok(ContractError::Unauthorized)));
+
+    client.grant_role(&Role::Treasurer, &operator, &solar_id);
+    client.update_token_info(&operator, &referral_raised);
+    assert_eq!(client.get_token_info(&solar.id).token.referral_fee_bps, 500);
+    client.update_token_info(&operator, &redirected);
+    assert_eq!(client.get_token_info(&solar.id).token.treasury, operator);
+    client.grant_role(&Role::Compliance, &operator, &solar_id);
+    client.update_token_info(&operator, &kyc_required);
+    assert!(client.get_token_info(&solar.id).token.require_kyc);
+
+    // Role changes stay with the admin
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &operator,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "grant_role",
+                args: (Role::Pauser, operator.clone(), None::<String>).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_grant_role(&Role::Pauser, &operator, &None);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_kyc_gates_investment() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let compliance = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    let token = default_token(&env, &contract_id);
+    client.init(&admin, &usdc.address, &token);
+    client.grant_role(&Role::Compliance, &compliance, &None);
+
+    let mut solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    solar.require_kyc = true;
+    solar.blocked_jurisdictions = vec![&env, String::from_str(&env, "KP")];
+    client.register_token(&solar);
+
+    // Tokens without the requirement stay open to anyone
+    client.create_investment(&buyer, &token.id, &100000000, &None);
+
+    let result = client.try_create_investment(&buyer, &solar.id, &100000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::NotVerified)));
+
+    let record = KycRecord {
+        status: KycStatus::Verified,
+        expires_at: 1000,
+        jurisdiction: String::from_str(&env, "CL"),
+    };
+    client.set_kyc(&compliance, &buyer, &record);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("KYC"), buyer.clone()).into_val(&env),
+                KycUpdated { schema_version: EVENT_SCHEMA_VERSION, investor: buyer.clone(), record: record.clone() }.into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(client.get_kyc(&buyer), Some(record.clone()));
+    client.create_investment(&buyer, &solar.id, &100000000, &None);
+
+    env.ledger().with_mut(|li| li.timestamp = 1000);
+    let result = client.try_create_investment(&buyer, &solar.id, &100000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::KycExpired)));
+
+    let blocked = KycRecord {
+        status: KycStatus::Verified,
+        expires_at: 5000,
+        jurisdiction: String::from_str(&env, "KP"),
+    };
+    client.set_kyc(&compliance, &buyer, &blocked);
+    let result = client.try_create_investment(&buyer, &solar.id, &100000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::JurisdictionBlocked)));
+
+    let revoked = KycRecord { status: KycStatus::Revoked, ..record };
+    client.set_kyc(&compliance, &buyer, &revoked);
+    let result = client.try_create_investment(&buyer, &solar.id, &100000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::NotVerified)));
+}
+
+#[test]
+fn test_kyc_managed_by_compliance_role() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let operator = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let payment_token = Address::generate(&env);
+    client.init(&admin, &payment_token, &default_token(&env, &contract_id));
+
+    let record = KycRecord {
+        status: KycStatus::Verified,
+        expires_at: 1000,
+        jurisdiction: String::from_str(&env, "CL"),
+    };
+    assert_eq!(client.try_set_kyc(&operator, &buyer, &record), Err(Ok(ContractError::Unauthorized)));
+    assert_eq!(client.get_kyc(&buyer), None);
+
+    // Blocking jurisdictions only makes sense when KYC is required
+    let mut solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    solar.blocked_jurisdictions = vec![&env, String::from_str(&env, "KP")];
+    assert_eq!(client.try_register_token(&solar), Err(Ok(ContractError::InvalidTokenConfig)));
+}
+
+#[test]
+fn test_external_compliance_contract() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let issuer = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    let compliance_id = env.register(MockCompliance, ());
+    let compliance = MockComplianceClient::new(&env, &compliance_id);
+    compliance.init(&issuer);
+
+    client.init(&admin, &usdc.address, &default_token(&env, &contract_id));
+    let mut solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    solar.compliance_contract = Some(compliance_id.clone());
+    client.register_token(&solar);
+
+    let result = client.try_create_investment(&buyer, &solar.id, &100000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::NotEligible)));
+
+    // The registry sees the amount and can approve up to a limit
+    compliance.allow(&buyer, &solar.id, &200000000);
+    client.create_investment(&buyer, &solar.id, &200000000, &None);
+    let result = client.try_create_investment(&buyer, &solar.id, &300000000, &None);
+    assert_eq!(result, Err(Ok(ContractError::NotEligible)));
+    assert_eq!(usdc.balance(&buyer), 800000000);
+
+    // The external registry replaces the built-in allowlist rather than stacking on it
+    let mut wind = make_token(&env, &contract_id, "WIND001", "Wind Park", 1000000, 5000000000);
+    wind.compliance_contract = Some(compliance_id);
+    wind.require_kyc = true;
+    assert_eq!(client.try_register_token(&wind), Err(Ok(ContractError::InvalidTokenConfig)));
+}
+
+#[test]
+fn test_yield_distribution() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let treasurer = Address::generate(&env);
+    let early_buyer = Address::generate(&env);
+    let late_buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&early_buyer, &1000000000); // $1K
+    usdc_admin.mint(&late_buyer, &1000000000); // $1K
+    usdc_admin.mint(&treasurer, &1000000000); // $1K
+
+    let token = default_token(&env, &contract_id);
+    client.init(&admin, &usdc.address, &token);
+    client.grant_role(&Role::Treasurer, &treasurer, &Some(token.id.clone()));
+
+    // Nothing has completed yet, so there is nobody to pay
+    let result = client.try_deposit_yield(&treasurer, &token.id, &30000000);
+    assert_eq!(result, Err(Ok(ContractError::NoYieldHolders)));
+
+    let early_id = client.create_investment(&early_buyer, &token.id, &300000000, &None); // $300
+    client.update_investment_status(&admin, &early_id, &InvestmentStatus::Completed);
+    assert_eq!(client.get_expected_annual_yield(&token.id), 37500000); // 12.5% of $300
+
+    client.deposit_yield(&treasurer, &token.id, &30000000); // $30
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("YIELDDEP"), token.id.clone()).into_val(&env),
+                YieldDeposited {
+                    schema_version: EVENT_SCHEMA_VERSION,
+                    token_id: token.id.clone(),
+                    operator: treasurer.clone(),
+                    amount: 30000000,
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(client.pending_yield(&early_buyer, &token.id), 30000000);
+
+    // A pending investment earns nothing, a completed one earns from the next deposit on
+    let late_id = client.create_investment(&late_buyer, &token.id, &100000000, &None); // $100
+    client.deposit_yield(&treasurer, &token.id, &6000000); // $6
+    assert_eq!(client.pending_yield(&early_buyer, &token.id), 36000000);
+    assert_eq!(client.pending_yield(&late_buyer, &token.id), 0);
+
+    client.update_investment_status(&admin, &late_id, &InvestmentStatus::Completed);
+    assert_eq!(client.pending_yield(&late_buyer, &token.id), 0);
+    client.deposit_yield(&treasurer, &token.id, &40000000); // $40 over $400
+    assert_eq!(client.pending_yield(&early_buyer, &token.id), 66000000);
+    assert_eq!(client.pending_yield(&late_buyer, &token.id), 10000000);
+
+    assert_eq!(client.claim_yield(&early_buyer, &token.id), 66000000);
+    assert_eq!(env.auths()[0].0, early_buyer);
+    assert_eq!(usdc.balance(&early_buyer), 766000000);
+    assert_eq!(client.pending_yield(&early_buyer, &token.id), 0);
+    assert_eq!(client.claim_yield(&early_buyer, &token.id), 0);
+
+    assert_eq!(client.claim_yield(&late_buyer, &token.id), 10000000);
+    assert_eq!(usdc.balance(&contract_id), 0);
+}
+
+#[test]
+fn test_yield_follows_shares() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let treasurer = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let friend = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+    usdc_admin.mint(&treasurer, &1000000000); // $1K
+
+    let token = default_token(&env, &contract_id);
+    client.init(&admin, &usdc.address, &token);
+    client.grant_role(&Role::Treasurer, &treasurer, &Some(token.id.clone()));
+
+    let investment_id = client.create_investment(&buyer, &token.id, &400000000, &None); // $400
+    client.update_investment_status(&admin, &investment_id, &InvestmentStatus::Completed);
+    client.deposit_yield(&treasurer, &token.id, &40000000); // $40
+
+    // Yield accrued so far stays with the seller, later yield goes to whoever holds the shares
+    let share_token = TokenClient::new(&env, &token.share_token);
+    share_token.transfer(&buyer, &friend, &1000000000); // A quarter of the 400 shares
+    assert_eq!(client.pending_yield(&buyer, &token.id), 40000000);
+    assert_eq!(client.pending_yield(&friend, &token.id), 0);
+
+    client.deposit_yield(&treasurer, &token.id, &20000000); // $20
+    assert_eq!(client.pending_yield(&buyer, &token.id), 55000000);
+    assert_eq!(client.pending_yield(&friend, &token.id), 5000000);
+
+    // Burned shares stop earning, and the rest split the next deposit
+    share_token.burn(&friend, &1000000000);
+    client.deposit_yield(&treasurer, &token.id, &30000000); // $30
+    assert_eq!(client.pending_yield(&buyer, &token.id), 85000000);
+    assert_eq!(client.pending_yield(&friend, &token.id), 5000000);
+
+    assert_eq!(client.claim_yield(&friend, &token.id), 5000000);
+    assert_eq!(client.claim_yield(&buyer, &token.id), 85000000);
+    assert_eq!(usdc.balance(&contract_id), 0);
+
+    // Only the registered share token can move positions
+    let result = client.try_on_share_transfer(&Address::generate(&env), &buyer, &Some(friend.clone()), &1);
+    assert_eq!(result, Err(Ok(ContractError::TokenNotFound)));
+}
+
+#[test]
+fn test_yield_with_18_decimal_shares() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let treasurer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&treasurer, &1000000000); // $1K
+
+    let share_token = env.register(ShareToken, ());
+    ShareTokenAdminClient::new(&env, &share_token).init(&contract_id, &18, &String::from_str(&env, "EVC"), &String::from_str(&env, "EVC"));
+    let token = TokenInfo { share_token, ..default_token(&env, &contract_id) };
+    client.init(&admin, &usdc.address, &token);
+    client.grant_role(&Role::Treasurer, &treasurer, &Some(token.id.clone()));
+
+    let buyers = [Address::generate(&env), Address::generate(&env), Address::generate(&env)];
+    for buyer in buyers.iter() {
+        usdc_admin.mint(buyer, &100000000); // $100
+        let investment_id = client.create_investment(buyer, &token.id, &100000000, &None);
+        client.update_investment_status(&admin, &investment_id, &InvestmentStatus::Completed);
+    }
+    assert_eq!(TokenClient::new(&env, &token.share_token).balance(&buyers[0]), 100000000000000000000); // 100 shares
+
+    // A deposit too small to split over 300 shares is carried, not lost
+    client.deposit_yield(&treasurer, &token.id, &1);
+    assert_eq!(client.pending_yield(&buyers[0], &token.id), 0);
+
+    client.deposit_yield(&treasurer, &token.id, &29999999);
+    for buyer in buyers.iter() {
+        assert_eq!(client.pending_yield(buyer, &token.id), 10000000); // $1 each
+        assert_eq!(client.claim_yield(buyer, &token.id), 10000000);
+    }
+    assert_eq!(usdc.balance(&contract_id), 0);
+}
+
+#[test]
+fn test_deposit_yield_requires_treasurer() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let operator = Address::generate(&env);
+    let (usdc, _) = create_payment_token(&env, &admin);
+    let token = default_token(&env, &contract_id);
+    client.init(&admin, &usdc.address, &token);
+
+    let result = client.try_deposit_yield(&operator, &token.id, &30000000);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+}
+
+#[test]
+fn test_widget_registry() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let site_owner = Address::generate(&env);
+    let payment_token = Address::generate(&env);
+    let token = default_token(&env, &contract_id);
+    client.init(&admin, &payment_token, &token);
+
+    let widget_hash = String::from_str(&env, "dob-1x2y3z-a1b2c3");
+    client.register_widget(&admin, &site_owner, &widget_hash, &token.id, &site_owner);
+    assert_eq!(env.auths()[0].0, admin);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("WIDGETREG"), token.id.clone()).into_val(&env),
+                WidgetRegistered {
+                    schema_version: EVENT_SCHEMA_VERSION,
+                    widget_hash: widget_hash.clone(),
+                    token_id: token.id.clone(),
+                    owner: site_owner.clone(),
+                    payout: site_owner.clone(),
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(
+        client.get_widget(&widget_hash),
+        Widget {
+            owner: site_owner.clone(),
+            payout: site_owner.clone(),
+            token_id: token.id.clone(),
+            active: true,
+            created_at: 0,
+        }
+    );
+
+    let result = client.try_register_widget(&admin, &Address::generate(&env), &widget_hash, &token.id, &site_owner);
+    assert_eq!(result, Err(Ok(ContractError::WidgetAlreadyExists)));
+    let other_hash = String::from_str(&env, "dob-9z8y7x-d4e5f6");
+    let result = client.try_register_widget(&admin, &site_owner, &other_hash, &String::from_str(&env, "UNKNOWN"), &site_owner);
+    assert_eq!(result, Err(Ok(ContractError::TokenNotFound)));
+    assert_eq!(client.try_get_widget(&other_hash), Err(Ok(ContractError::WidgetNotFound)));
+
+    // Site owners cannot register their own widgets; only the admin or the token's manager can
+    let result = client.try_register_widget(&site_owner, &site_owner, &other_hash, &token.id, &site_owner);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+    let manager = Address::generate(&env);
+    client.grant_role(&Role::TokenManager, &manager, &Some(token.id.clone()));
+    client.register_widget(&manager, &site_owner, &other_hash, &token.id, &site_owner);
+    assert_eq!(env.auths()[0].0, manager);
+    assert_eq!(client.get_widget(&other_hash).owner, site_owner);
+
+    client.deactivate_widget(&widget_hash);
+    assert_eq!(env.auths()[0].0, site_owner);
+    assert!(!client.get_widget(&widget_hash).active);
+}
+
+#[test]
+fn test_investment_attributed_to_widget() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let site_owner = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    let token = default_token(&env, &contract_id);
+    client.init(&admin, &usdc.address, &token);
+    let solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    client.register_token(&solar);
+
+    let widget_hash = String::from_str(&env, "dob-1x2y3z-a1b2c3");
+    client.register_widget(&admin, &site_owner, &widget_hash, &token.id, &site_owner);
+
+    let investment_id = client.create_investment(&buyer, &token.id, &100000000, &Some(widget_hash.clone()));
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("INVESTED"), token.id.clone(), buyer.clone()).into_val(&env),
+                InvestmentCreated {
+                    schema_version: EVENT_SCHEMA_VERSION,
+                    investment_id,
+                    token_id: token.id.clone(),
+                    buyer: buyer.clone(),
+                    amount: 100000000,
+                    widget_hash: Some(widget_hash.clone()),
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(client.get_investment(&investment_id).widget_hash, Some(widget_hash.clone()));
+
+    // A widget only drives investments into its own token
+    let result = client.try_create_investment(&buyer, &solar.id, &100000000, &Some(widget_hash.clone()));
+    assert_eq!(result, Err(Ok(ContractError::WidgetTokenMismatch)));
+
+    let unknown_hash = Some(String::from_str(&env, "dob-unknown"));
+    let result = client.try_create_investment(&buyer, &token.id, &100000000, &unknown_hash);
+    assert_eq!(result, Err(Ok(ContractError::WidgetNotFound)));
+
+    client.deactivate_widget(&widget_hash);
+    let result = client.try_create_investment(&buyer, &token.id, &100000000, &Some(widget_hash));
+    assert_eq!(result, Err(Ok(ContractError::WidgetInactive)));
+
+    // Direct investments carry no attribution
+    let investment_id = client.create_investment(&buyer, &token.id, &100000000, &None);
+    assert_eq!(client.get_investment(&investment_id).widget_hash, None);
+}
+
+#[test]
+fn test_referral_fee_on_widget_investment() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let site_owner = Address::generate(&env);
+    let payout = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    let mut token = default_token(&env, &contract_id);
+    token.referral_fee_bps = 150; // 1.5%
+    client.init(&admin, &usdc.address, &token);
+
+    let widget_hash = String::from_str(&env, "dob-1x2y3z-a1b2c3");
+    client.register_widget(&admin, &site_owner, &widget_hash, &token.id, &payout);
+
+    let widget_id = client.create_investment(&buyer, &token.id, &200000000, &Some(widget_hash.clone()));
+    let direct_id = client.create_investment(&buyer, &token.id, &100000000, &None);
+
+    client.update_investment_status(&admin, &widget_id, &InvestmentStatus::Completed);
+    let settlement = Settlement {
+        refunded: 0,
+        proceeds: 197000000,
+        referral_fee: 3000000,
+        referral_payout: Some(payout.clone()),
+        platform_fee: 0,
+    };
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("INVSTAT"), token.id.clone(), buyer.clone()).into_val(&env),
+                StatusChanged {
+                    schema_version: EVENT_SCHEMA_VERSION,
+                    investment_id: widget_id,
+                    token_id: token.id.clone(),
+                    buyer: buyer.clone(),
+                    old_status: InvestmentStatus::Pending,
+                    new_status: InvestmentStatus::Completed,
+                    settlement,
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(usdc.balance(&token.treasury), 197000000);
+    assert_eq!(client.get_referral_balance(&payout), 3000000);
+
+    // Direct investments pay the treasury in full
+    client.update_investment_status(&admin, &direct_id, &InvestmentStatus::Completed);
+    assert_eq!(usdc.balance(&token.treasury), 297000000);
+    assert_eq!(client.get_referral_balance(&payout), 3000000);
+
+    assert_eq!(client.claim_referral_fees(&payout), 3000000);
+    assert_eq!(env.auths()[0].0, payout);
+    assert_eq!(usdc.balance(&payout), 3000000);
+    assert_eq!(client.get_referral_balance(&payout), 0);
+    assert_eq!(usdc.balance(&contract_id), 0);
+
+    // Investing through your own widget, as its owner or its payout, earns no referral fee
+    for self_referrer in [site_owner.clone(), payout.clone()] {
+        usdc_admin.mint(&self_referrer, &100000000);
+        let investment_id = client.create_investment(&self_referrer, &token.id, &100000000, &Some(widget_hash.clone()));
+        client.update_investment_status(&admin, &investment_id, &InvestmentStatus::Completed);
+        assert_eq!(client.get_referral_balance(&payout), 0);
+    }
+    assert_eq!(usdc.balance(&token.treasury), 497000000);
+    assert_eq!(usdc.balance(&contract_id), 0);
+}
+
+#[test]
+fn test_referral_fee_not_charged_on_refund() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let site_owner = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    let mut token = default_token(&env, &contract_id);
+    token.referral_fee_bps = 150; // 1.5%
+    client.init(&admin, &usdc.address, &token);
+
+    let widget_hash = String::from_str(&env, "dob-1x2y3z-a1b2c3");
+    client.register_widget(&admin, &site_owner, &widget_hash, &token.id, &site_owner);
+    let investment_id = client.create_investment(&buyer, &token.id, &200000000, &Some(widget_hash));
+
+    client.update_investment_status(&admin, &investment_id, &InvestmentStatus::Failed);
+    assert_eq!(usdc.balance(&buyer), 1000000000);
+    assert_eq!(client.get_referral_balance(&site_owner), 0);
+
+    // Above the 5% cap
+    let mut solar = make_token(&env, &contract_id, "SOLAR001", "Solar Farm", 1000000, 5000000000);
+    solar.referral_fee_bps = 501;
+    assert_eq!(client.try_register_token(&solar), Err(Ok(ContractError::InvalidTokenConfig)));
+}
+
+#[test]
+fn test_platform_fee_timelock() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let platform_treasury = Address::generate(&env);
+    let payment_token = Address::generate(&env);
+    client.init(&admin, &payment_token, &default_token(&env, &contract_id));
+    assert_eq!(client.get_fee_config(), None);
+
+    // Above the 5% cap
+    let result = client.try_set_fee_config(&501, &platform_treasury);
+    assert_eq!(result, Err(Ok(ContractError::InvalidFeeConfig)));
+
+    let effective_at = client.set_fee_config(&100, &platform_treasury);
+    assert_eq!(effective_at, 7 * 24 * 60 * 60);
+    let scheduled = ScheduledFeeConfig {
+        config: FeeConfig { fee_bps: 100, treasury: platform_treasury.clone() },
+        effective_at,
+    };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("FEESCHED"),).into_val(&env),
+                FeeScheduled {
+                    schema_version: EVENT_SCHEMA_VERSION,
+                    config: scheduled.config.clone(),
+                    effective_at,
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(client.get_scheduled_fee_config(), Some(scheduled.clone()));
+    assert_eq!(client.get_fee_config(), None);
+
+    env.ledger().with_mut(|li| li.timestamp = effective_at);
+    assert_eq!(client.get_fee_config(), Some(scheduled.config.clone()));
+
+    // A later change leaves the current fee in place until its own delay passes
+    client.set_fee_config(&300, &platform_treasury);
+    assert_eq!(client.get_fee_config(), Some(scheduled.config));
+    env.ledger().with_mut(|li| li.timestamp = effective_at * 2);
+    assert_eq!(client.get_fee_config().unwrap().fee_bps, 300);
+}
+
+#[test]
+fn test_platform_fee_charged_on_completion() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(DobLinkContract, ());
+    let client = DobLinkContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let treasurer = Address::generate(&env);
+    let platform_treasury = Address::generate(&env);
+    let site_owner = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let (usdc, usdc_admin) = create_payment_token(&env, &admin);
+    usdc_admin.mint(&buyer, &1000000000); // $1K
+
+    let mut token = default_token(&env, &contract_id);
+    token.referral_fee_bps = 150; // 1.5%
+    client.init(&admin, &usdc.address, &token);
+    client.grant_role(&Role::Treasurer, &treasurer, &None);
+
+    let widget_hash = String::from_str(&env, "dob-1x2y3z-a1b2c3");
+    client.register_widget(&admin, &site_owner, &widget_hash, &token.id, &site_owner);
+
+    let effective_at = client.set_fee_config(&200, &platform_treasury); // 2%
+    env.ledger().with_mut(|li| li.timestamp = effective_at);
+
+    let investment_id = client.create_investment(&buyer, &token.id, &200000000, &Some(widget_hash));
+    let refunded_id = client.create_investment(&buyer, &token.id, &100000000, &None);
+    client.update_investment_status(&admin, &investment_id, &InvestmentStatus::Completed);
+    let settlement = Settlement {
+        refunded: 0,
+        proceeds: 193000000,
+        referral_fee: 3000000,
+        referral_payout: Some(site_owner.clone()),
+        platform_fee: 4000000,
+    };
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("INVSTAT"), token.id.clone(), buyer.clone()).into_val(&env),
+                StatusChanged {
+                    schema_version: EVENT_SCHEMA_VERSION,
+                    investment_id,
+                    token_id: token.id.clone(),
+                    buyer: buyer.clone(),
+                    old_status: InvestmentStatus::Pending,
+                    new_status: InvestmentStatus::Completed,
+                    settlement,
+                }
+                .into_val(&env),
+            ),
+        ]
+    );
+    assert_eq!(usdc.balance(&token.treasury), 193000000);
+    assert_eq!(client.get_accrued_fees(), 4000000);
+
+    // Refunds are not charged
+    client.update_investment_status(&admin, &refunded_id, &InvestmentStatus::Failed);
+    assert_eq!(client.get_accrued_fees(), 4000000);
+
+    // Fees only leave to the configured treasury, and only what has accrued
+    let other = Address::generate(&env);
+    let result = client.try_withdraw_fees(&treasurer, &other, &1000000);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+    let result = client.try_withdraw_fees(&treasurer, &platform_treasury, &4000001);
+    assert_eq!(result, Err(Ok(ContractError::InsufficientFees)));
+    let result = client.try_withdraw_fees(&buyer, &platform_treasury, &1000000);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+
+    client.withdraw_fees(&treasurer, &platform_treasury, &3000000);
+    assert_eq!(env.auths()[0].0, treasurer);
+    assert_eq!(usdc.balance(&platform_treasury), 3000000);
+    assert_eq!(client.get_accrued_fees(), 1000000);
+}
@@ -0,0 +1,39 @@
+#![no_std]
+use soroban_sdk::{contract, contractimpl, symbol_short, Address, Env, String};
+
+// Ledger TTL bounds (~5s per ledger)
+const DAY_IN_LEDGERS: u32 = 17280;
+const INSTANCE_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+const INSTANCE_LIFETIME_THRESHOLD: u32 = INSTANCE_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+// Reference compliance registry for DobLink's external compliance hook.
+// The registry admin approves a buyer for a token up to a maximum amount per investment.
+#[contract]
+pub struct MockCompliance;
+
+#[contractimpl]
+impl MockCompliance {
+    // Initialize the registry with the address allowed to approve buyers
+    pub fn init(env: Env, admin: Address) {
+        env.storage().instance().set(&symbol_short!("ADMIN"), &admin);
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+    }
+
+    // Approve a buyer for a token up to max_amount per investment, 0 revokes (admin only)
+    pub fn allow(env: Env, buyer: Address, token_id: String, max_amount: i128) {
+        let admin: Address = env.storage().instance().get(&symbol_short!("ADMIN")).unwrap();
+        admin.require_auth();
+
+        let allowed_key = (symbol_short!("ALLOWED"), buyer, token_id);
+        env.storage().persistent().set(&allowed_key, &max_amount);
+    }
+
+    // Check whether a buyer may invest the given amount in a token
+    pub fn is_eligible(env: Env, buyer: Address, token_id: String, amount: i128) -> bool {
+        let allowed_key = (symbol_short!("ALLOWED"), buyer, token_id);
+        let max_amount: i128 = env.storage().persistent().get(&allowed_key).unwrap_or(0);
+        amount > 0 && amount <= max_amount
+    }
+}
+
+mod test;
@@ -0,0 +1,54 @@
+#![cfg(test)]
+
+use super::*;
+use soroban_sdk::testutils::{Address as _, MockAuth, MockAuthInvoke};
+use soroban_sdk::{Address, Env, IntoVal, String};
+
+#[test]
+fn test_is_eligible() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(MockCompliance, ());
+    let client = MockComplianceClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    client.init(&admin);
+
+    assert!(!client.is_eligible(&buyer, &token_id, &100));
+
+    client.allow(&buyer, &token_id, &1000);
+    assert!(client.is_eligible(&buyer, &token_id, &1000));
+    assert!(!client.is_eligible(&buyer, &token_id, &1001));
+    assert!(!client.is_eligible(&buyer, &String::from_str(&env, "SOLAR001"), &100));
+
+    client.allow(&buyer, &token_id, &0);
+    assert!(!client.is_eligible(&buyer, &token_id, &100));
+}
+
+#[test]
+fn test_only_admin_allows() {
+    let env = Env::default();
+    let contract_id = env.register(MockCompliance, ());
+    let client = MockComplianceClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let token_id = String::from_str(&env, "EVCHARGER001");
+    client.init(&admin);
+
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &buyer,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "allow",
+                args: (buyer.clone(), token_id.clone(), 1000_i128).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_allow(&buyer, &token_id, &1000);
+    assert!(result.is_err());
+    assert!(!client.is_eligible(&buyer, &token_id, &100));
+}
@@ -0,0 +1,326 @@
+#![no_std]
+use soroban_sdk::token::TokenInterface;
+use soroban_sdk::{
+    contract, contractclient, contracterror, contractimpl, contracttype, panic_with_error, symbol_short,
+    Address, Env, String,
+};
+
+// Ledger TTL bounds (~5s per ledger)
+const DAY_IN_LEDGERS: u32 = 17280;
+const INSTANCE_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+const INSTANCE_LIFETIME_THRESHOLD: u32 = INSTANCE_BUMP_AMOUNT - DAY_IN_LEDGERS;
+const BALANCE_BUMP_AMOUNT: u32 = 180 * DAY_IN_LEDGERS;
+const BALANCE_LIFETIME_THRESHOLD: u32 = BALANCE_BUMP_AMOUNT - 7 * DAY_IN_LEDGERS;
+
+// SEP-41 share token representing a claim on one DobLink RWA offering.
+// The admin (the DobLink contract) is the only address allowed to mint.
+#[contract]
+pub struct ShareToken;
+
+// Contract told about every transfer and burn, so it can track who holds the shares.
+// Mints are not reported: only the admin mints, and it records its own issuance.
+#[contractclient(name = "TransferHookClient")]
+pub trait TransferHook {
+    fn on_share_transfer(env: Env, share_token: Address, from: Address, to: Option<Address>, amount: i128);
+}
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum ShareTokenError {
+    AlreadyInitialized = 1,
+    NotInitialized = 2,
+    NegativeAmount = 3,
+    InsufficientBalance = 4,
+    InsufficientAllowance = 5,
+    InvalidExpiration = 6,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AllowanceValue {
+    pub amount: i128,
+    pub expiration_ledger: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenMetadata {
+    pub decimal: u32,
+    pub name: String,
+    pub symbol: String,
+}
+
+#[contractimpl]
+impl ShareToken {
+    // Initialize the share token with its mint authority and metadata
+    pub fn init(env: Env, admin: Address, decimal: u32, name: String, symbol: String) {
+        let admin_key = symbol_short!("ADMIN");
+        if env.storage().instance().has(&admin_key) {
+            panic_with_error!(&env, ShareTokenError::AlreadyInitialized);
+        }
+        env.storage().instance().set(&admin_key, &admin);
+
+        let metadata_key = symbol_short!("METADATA");
+        env.storage().instance().set(&metadata_key, &TokenMetadata { decimal, name, symbol });
+        Self::extend_instance(&env);
+    }
+
+    // Get the mint authority
+    pub fn admin(env: Env) -> Address {
+        Self::read_admin(&env)
+    }
+
+    // Hand the mint authority to a new address (admin only)
+    pub fn set_admin(env: Env, new_admin: Address) {
+        let admin = Self::read_admin(&env);
+        admin.require_auth();
+
+        let admin_key = symbol_short!("ADMIN");
+        env.storage().instance().set(&admin_key, &new_admin);
+        Self::extend_instance(&env);
+
+        env.events().publish((symbol_short!("set_admin"), admin), new_admin);
+    }
+
+    // Report transfers and burns to the given contract from now on (admin only)
+    pub fn set_transfer_hook(env: Env, hook: Address) {
+        let admin = Self::read_admin(&env);
+        admin.require_auth();
+
+        env.storage().instance().set(&symbol_short!("HOOK"), &hook);
+        Self::extend_instance(&env);
+
+        env.events().publish((symbol_short!("set_hook"), admin), hook);
+    }
+
+    // Get the contract transfers and burns are reported to, if any
+    pub fn transfer_hook(env: Env) -> Option<Address> {
+        env.storage().instance().get(&symbol_short!("HOOK"))
+    }
+
+    // Mint new shares to an investor (admin only)
+    pub fn mint(env: Env, to: Address, amount: i128) {
+        Self::check_nonnegative_amount(&env, amount);
+        let admin = Self::read_admin(&env);
+        admin.require_auth();
+
+        Self::receive_balance(&env, &to, amount);
+        let supply_key = symbol_short!("SUPPLY");
+        let supply: i128 = env.storage().instance().get(&supply_key).unwrap_or(0);
+        env.storage().instance().set(&supply_key, &(supply + amount));
+        Self::extend_instance(&env);
+
+        env.events().publish((symbol_short!("mint"), admin, to), amount);
+    }
+
+    // Get the number of shares in circulation
+    pub fn total_supply(env: Env) -> i128 {
+        let supply_key = symbol_short!("SUPPLY");
+        env.storage().instance().get(&supply_key).unwrap_or(0)
+    }
+
+    // Helper function to read the mint authority
+    fn read_admin(env: &Env) -> Address {
+        let admin_key = symbol_short!("ADMIN");
+        env.storage()
+            .instance()
+            .get(&admin_key)
+            .unwrap_or_else(|| panic_with_error!(env, ShareTokenError::NotInitialized))
+    }
+
+    // Helper function to read the token metadata
+    fn read_metadata(env: &Env) -> TokenMetadata {
+        let metadata_key = symbol_short!("METADATA");
+        env.storage()
+            .instance()
+            .get(&metadata_key)
+            .unwrap_or_else(|| panic_with_error!(env, ShareTokenError::NotInitialized))
+    }
+
+    // Helper function to read an address balance
+    fn read_balance(env: &Env, id: &Address) -> i128 {
+        let balance_key = (symbol_short!("BALANCE"), id.clone());
+        match env.storage().persistent().get::<_, i128>(&balance_key) {
+            Some(balance) => {
+                env.storage().persistent().extend_ttl(&balance_key, BALANCE_LIFETIME_THRESHOLD, BALANCE_BUMP_AMOUNT);
+                balance
+            }
+            None => 0,
+        }
+    }
+
+    // Helper function to write an address balance
+    fn write_balance(env: &Env, id: &Address, amount: i128) {
+        let balance_key = (symbol_short!("BALANCE"), id.clone());
+        env.storage().persistent().set(&balance_key, &amount);
+        env.storage().persistent().extend_ttl(&balance_key, BALANCE_LIFETIME_THRESHOLD, BALANCE_BUMP_AMOUNT);
+    }
+
+    // Helper function to credit an address
+    fn receive_balance(env: &Env, id: &Address, amount: i128) {
+        let balance = Self::read_balance(env, id);
+        Self::write_balance(env, id, balance + amount);
+    }
+
+    // Helper function to debit an address
+    fn spend_balance(env: &Env, id: &Address, amount: i128) {
+        let balance = Self::read_balance(env, id);
+        if balance < amount {
+            panic_with_error!(env, ShareTokenError::InsufficientBalance);
+        }
+        Self::write_balance(env, id, balance - amount);
+    }
+
+    // Helper function to read an allowance, treating expired ones as zero
+    fn read_allowance(env: &Env, from: &Address, spender: &Address) -> AllowanceValue {
+        let allowance_key = (symbol_short!("ALLOWANCE"), from.clone(), spender.clone());
+        match env.storage().temporary().get::<_, AllowanceValue>(&allowance_key) {
+            Some(allowance) if allowance.expiration_ledger >= env.ledger().sequence() => allowance,
+            _ => AllowanceValue { amount: 0, expiration_ledger: 0 },
+        }
+    }
+
+    // Helper function to write an allowance that lives until its expiration ledger
+    fn write_allowance(env: &Env, from: &Address, spender: &Address, amount: i128, expiration_ledger: u32) {
+        if amount > 0 && expiration_ledger < env.ledger().sequence() {
+            panic_with_error!(env, ShareTokenError::InvalidExpiration);
+        }
+
+        let allowance_key = (symbol_short!("ALLOWANCE"), from.clone(), spender.clone());
+        let allowance = AllowanceValue { amount, expiration_ledger };
+        env.storage().temporary().set(&allowance_key, &allowance);
+
+        if amount > 0 {
+            let live_for = expiration_ledger - env.ledger().sequence();
+            env.storage().temporary().extend_ttl(&allowance_key, live_for, live_for);
+        }
+    }
+
+    // Helper function to consume part of an allowance
+    fn spend_allowance(env: &Env, from: &Address, spender: &Address, amount: i128) {
+        let allowance = Self::read_allowance(env, from, spender);
+        if allowance.amount < amount {
+            panic_with_error!(env, ShareTokenError::InsufficientAllowance);
+        }
+        if amount > 0 {
+            Self::write_allowance(env, from, spender, allowance.amount - amount, allowance.expiration_ledger);
+        }
+    }
+
+    // Helper function to reject negative amounts
+    fn check_nonnegative_amount(env: &Env, amount: i128) {
+        if amount < 0 {
+            panic_with_error!(env, ShareTokenError::NegativeAmount);
+        }
+    }
+
+    // Helper function to keep the token instance alive
+    fn extend_instance(env: &Env) {
+        env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+    }
+
+    // Helper function to report a transfer, or a burn when to is None, to the hook
+    fn notify_hook(env: &Env, from: &Address, to: Option<Address>, amount: i128) {
+        if let Some(hook) = Self::transfer_hook(env.clone()) {
+            TransferHookClient::new(env, &hook).on_share_transfer(&env.current_contract_address(), from, &to, &amount);
+        }
+    }
+
+    // Helper function to take burned shares out of circulation
+    fn reduce_supply(env: &Env, amount: i128) {
+        let supply_key = symbol_short!("SUPPLY");
+        let supply: i128 = env.storage().instance().get(&supply_key).unwrap_or(0);
+        env.storage().instance().set(&supply_key, &(supply - amount));
+    }
+}
+
+#[contractimpl]
+impl TokenInterface for ShareToken {
+    fn allowance(env: Env, from: Address, spender: Address) -> i128 {
+        Self::extend_instance(&env);
+        Self::read_allowance(&env, &from, &spender).amount
+    }
+
+    fn approve(env: Env, from: Address, spender: Address, amount: i128, expiration_ledger: u32) {
+        from.require_auth();
+        Self::check_nonnegative_amount(&env, amount);
+        Self::extend_instance(&env);
+
+        Self::write_allowance(&env, &from, &spender, amount, expiration_ledger);
+
+        env.events().publish(
+            (symbol_short!("approve"), from, spender),
+            (amount, expiration_ledger),
+        );
+    }
+
+    fn balance(env: Env, id: Address) -> i128 {
+        Self::extend_instance(&env);
+        Self::read_balance(&env, &id)
+    }
+
+    fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+        from.require_auth();
+        Self::check_nonnegative_amount(&env, amount);
+        Self::extend_instance(&env);
+
+        Self::spend_balance(&env, &from, amount);
+        Self::receive_balance(&env, &to, amount);
+        Self::notify_hook(&env, &from, Some(to.clone()), amount);
+
+        env.events().publish((symbol_short!("transfer"), from, to), amount);
+    }
+
+    fn transfer_from(env: Env, spender: Address, from: Address, to: Address, amount: i128) {
+        spender.require_auth();
+        Self::check_nonnegative_amount(&env, amount);
+        Self::extend_instance(&env);
+
+        Self::spend_allowance(&env, &from, &spender, amount);
+        Self::spend_balance(&env, &from, amount);
+        Self::receive_balance(&env, &to, amount);
+        Self::notify_hook(&env, &from, Some(to.clone()), amount);
+
+        env.events().publish((symbol_short!("transfer"), from, to), amount);
+    }
+
+    fn burn(env: Env, from: Address, amount: i128) {
+        from.require_auth();
+        Self::check_nonnegative_amount(&env, amount);
+        Self::extend_instance(&env);
+
+        Self::spend_balance(&env, &from, amount);
+        Self::reduce_supply(&env, amount);
+        Self::notify_hook(&env, &from, None, amount);
+
+        env.events().publish((symbol_short!("burn"), from), amount);
+    }
+
+    fn burn_from(env: Env, spender: Address, from: Address, amount: i128) {
+        spender.require_auth();
+        Self::check_nonnegative_amount(&env, amount);
+        Self::extend_instance(&env);
+
+        Self::spend_allowance(&env, &from, &spender, amount);
+        Self::spend_balance(&env, &from, amount);
+        Self::reduce_supply(&env, amount);
+        Self::notify_hook(&env, &from, None, amount);
+
+        env.events().publish((symbol_short!("burn"), from), amount);
+    }
+
+    fn decimals(env: Env) -> u32 {
+        Self::read_metadata(&env).decimal
+    }
+
+    fn name(env: Env) -> String {
+        Self::read_metadata(&env).name
+    }
+
+    fn symbol(env: Env) -> String {
+        Self::read_metadata(&env).symbol
+    }
+}
+
+mod test;
@@ -0,0 +1,223 @@
+#![cfg(test)]
+
+use super::*;
+use soroban_sdk::testutils::{Address as _, Ledger, MockAuth, MockAuthInvoke};
+use soroban_sdk::{contract, contractimpl, symbol_short, Address, Env, IntoVal, String};
+
+fn create_share_token<'a>(env: &Env, admin: &Address) -> ShareTokenClient<'a> {
+    let contract_id = env.register(ShareToken, ());
+    let client = ShareTokenClient::new(env, &contract_id);
+    client.init(
+        admin,
+        &7,
+        &String::from_str(env, "Electric Vehicle Charging Network Shares"),
+        &String::from_str(env, "EVC"),
+    );
+    client
+}
+
+#[test]
+fn test_metadata() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let share_token = create_share_token(&env, &admin);
+
+    assert_eq!(share_token.admin(), admin);
+    assert_eq!(share_token.decimals(), 7);
+    assert_eq!(share_token.name(), String::from_str(&env, "Electric Vehicle Charging Network Shares"));
+    assert_eq!(share_token.symbol(), String::from_str(&env, "EVC"));
+    assert_eq!(share_token.total_supply(), 0);
+}
+
+#[test]
+fn test_init_only_once() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let share_token = create_share_token(&env, &admin);
+
+    let result = share_token.try_init(
+        &Address::generate(&env),
+        &2,
+        &String::from_str(&env, "Other"),
+        &String::from_str(&env, "OTH"),
+    );
+    assert_eq!(result, Err(Ok(ShareTokenError::AlreadyInitialized.into())));
+}
+
+#[test]
+fn test_mint_transfer_burn() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let investor = Address::generate(&env);
+    let other = Address::generate(&env);
+    let share_token = create_share_token(&env, &admin);
+
+    share_token.mint(&investor, &1000);
+    assert_eq!(env.auths()[0].0, admin);
+    assert_eq!(share_token.balance(&investor), 1000);
+    assert_eq!(share_token.total_supply(), 1000);
+
+    share_token.transfer(&investor, &other, &400);
+    assert_eq!(env.auths()[0].0, investor);
+    assert_eq!(share_token.balance(&investor), 600);
+    assert_eq!(share_token.balance(&other), 400);
+
+    share_token.burn(&other, &100);
+    assert_eq!(share_token.balance(&other), 300);
+    assert_eq!(share_token.total_supply(), 900);
+
+    let result = share_token.try_transfer(&investor, &other, &601);
+    assert_eq!(result, Err(Ok(ShareTokenError::InsufficientBalance.into())));
+    let result = share_token.try_transfer(&investor, &other, &-1);
+    assert_eq!(result, Err(Ok(ShareTokenError::NegativeAmount.into())));
+}
+
+// Stands in for DobLink, remembering the last transfer it was told about
+#[contract]
+struct RecordingHook;
+
+#[contractimpl]
+impl RecordingHook {
+    pub fn on_share_transfer(env: Env, share_token: Address, from: Address, to: Option<Address>, amount: i128) {
+        share_token.require_auth();
+        env.storage().instance().set(&symbol_short!("LAST"), &(share_token, from, to, amount));
+    }
+
+    pub fn last(env: Env) -> Option<(Address, Address, Option<Address>, i128)> {
+        env.storage().instance().get(&symbol_short!("LAST"))
+    }
+}
+
+#[test]
+fn test_transfer_hook() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let investor = Address::generate(&env);
+    let other = Address::generate(&env);
+    let share_token = create_share_token(&env, &admin);
+    let hook = RecordingHookClient::new(&env, &env.register(RecordingHook, ()));
+
+    assert_eq!(share_token.transfer_hook(), None);
+    share_token.set_transfer_hook(&hook.address);
+    assert_eq!(env.auths()[0].0, admin);
+    assert_eq!(share_token.transfer_hook(), Some(hook.address.clone()));
+
+    // The admin records its own mints
+    share_token.mint(&investor, &1000);
+    assert_eq!(hook.last(), None);
+
+    share_token.transfer(&investor, &other, &400);
+    assert_eq!(hook.last(), Some((share_token.address.clone(), investor.clone(), Some(other.clone()), 400)));
+
+    share_token.approve(&investor, &other, &100, &200);
+    share_token.transfer_from(&other, &investor, &other, &100);
+    assert_eq!(hook.last(), Some((share_token.address.clone(), investor.clone(), Some(other.clone()), 100)));
+
+    share_token.burn(&other, &50);
+    assert_eq!(hook.last(), Some((share_token.address.clone(), other.clone(), None, 50)));
+}
+
+#[test]
+fn test_only_admin_sets_transfer_hook() {
+    let env = Env::default();
+    let contract_id = env.register(ShareToken, ());
+    let share_token = ShareTokenClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let attacker = Address::generate(&env);
+    share_token.init(&admin, &7, &String::from_str(&env, "Shares"), &String::from_str(&env, "SHR"));
+
+    let result = share_token
+        .mock_auths(&[MockAuth {
+            address: &attacker,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "set_transfer_hook",
+                args: (attacker.clone(),).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_set_transfer_hook(&attacker);
+    assert!(result.is_err());
+    assert_eq!(share_token.transfer_hook(), None);
+}
+
+#[test]
+fn test_only_admin_mints() {
+    let env = Env::default();
+    let contract_id = env.register(ShareToken, ());
+    let share_token = ShareTokenClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let attacker = Address::generate(&env);
+    share_token.init(&admin, &7, &String::from_str(&env, "Shares"), &String::from_str(&env, "SHR"));
+
+    let result = share_token
+        .mock_auths(&[MockAuth {
+            address: &attacker,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "mint",
+                args: (attacker.clone(), 1000_i128).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_mint(&attacker, &1000);
+    assert!(result.is_err());
+    assert_eq!(share_token.balance(&attacker), 0);
+}
+
+#[test]
+fn test_allowance() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let investor = Address::generate(&env);
+    let spender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let share_token = create_share_token(&env, &admin);
+    share_token.mint(&investor, &1000);
+
+    share_token.approve(&investor, &spender, &500, &200);
+    assert_eq!(share_token.allowance(&investor, &spender), 500);
+
+    share_token.transfer_from(&spender, &investor, &recipient, &300);
+    assert_eq!(env.auths()[0].0, spender);
+    assert_eq!(share_token.allowance(&investor, &spender), 200);
+    assert_eq!(share_token.balance(&recipient), 300);
+
+    let result = share_token.try_transfer_from(&spender, &investor, &recipient, &201);
+    assert_eq!(result, Err(Ok(ShareTokenError::InsufficientAllowance.into())));
+
+    share_token.burn_from(&spender, &investor, &200);
+    assert_eq!(share_token.balance(&investor), 500);
+    assert_eq!(share_token.total_supply(), 800);
+}
+
+#[test]
+fn test_allowance_expires() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let investor = Address::generate(&env);
+    let spender = Address::generate(&env);
+    let share_token = create_share_token(&env, &admin);
+    share_token.mint(&investor, &1000);
+
+    let expiration_ledger = env.ledger().sequence() + 10;
+    share_token.approve(&investor, &spender, &500, &expiration_ledger);
+    assert_eq!(share_token.allowance(&investor, &spender), 500);
+
+    env.ledger().with_mut(|li| li.sequence_number = expiration_ledger + 1);
+    assert_eq!(share_token.allowance(&investor, &spender), 0);
+
+    // An allowance cannot be created already expired
+    let result = share_token.try_approve(&investor, &spender, &500, &expiration_ledger);
+    assert_eq!(result, Err(Ok(ShareTokenError::InvalidExpiration.into())));
+}
@@ -0,0 +1,75 @@
+use dob_link_contract::{
+    InvestmentCreated, StatusChanged, TokenDelisted, TokenRegistered, WidgetDeactivated, WidgetRegistered,
+    YieldClaimed, YieldDeposited, EVENT_SCHEMA_VERSION,
+};
+use soroban_sdk::xdr::{Limits, ReadXdr, ScVal};
+use soroban_sdk::{Env, TryFromVal};
+
+use crate::rpc::RpcEvent;
+use crate::Error;
+
+// A DobLink event decoded into the contract's own payload types
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DobLinkEvent {
+    InvestmentCreated(InvestmentCreated),
+    StatusChanged(StatusChanged),
+    TokenRegistered(TokenRegistered),
+    TokenDelisted(TokenDelisted),
+    WidgetRegistered(WidgetRegistered),
+    WidgetDeactivated(WidgetDeactivated),
+    YieldDeposited(YieldDeposited),
+    YieldClaimed(YieldClaimed),
+    // Admin, role, KYC, pause, fee and token update events are recorded but not aggregated
+    Other(String),
+}
+
+// Decode an RPC event, checking its schema version before trusting the payload shape
+pub fn decode_event(env: &Env, event: &RpcEvent) -> Result<DobLinkEvent, Error> {
+    let name = event_name(event)?;
+    let value = ScVal::from_xdr_base64(&event.value, Limits::none())?;
+
+    let version = schema_version(&value);
+    if version != Some(EVENT_SCHEMA_VERSION) {
+        return Err(Error::UnsupportedSchema { event_id: event.id.clone(), version });
+    }
+
+    let decoded = match name.as_str() {
+        "INVESTED" => DobLinkEvent::InvestmentCreated(payload(env, event, &name, &value)?),
+        "INVSTAT" => DobLinkEvent::StatusChanged(payload(env, event, &name, &value)?),
+        "TOKENREG" => DobLinkEvent::TokenRegistered(payload(env, event, &name, &value)?),
+        "TOKENDEL" => DobLinkEvent::TokenDelisted(payload(env, event, &name, &value)?),
+        "WIDGETREG" => DobLinkEvent::WidgetRegistered(payload(env, event, &name, &value)?),
+        "WIDGETOFF" => DobLinkEvent::WidgetDeactivated(payload(env, event, &name, &value)?),
+        "YIELDDEP" => DobLinkEvent::YieldDeposited(payload(env, event, &name, &value)?),
+        "YIELDCLM" => DobLinkEvent::YieldClaimed(payload(env, event, &name, &value)?),
+        _ => DobLinkEvent::Other(name),
+    };
+    Ok(decoded)
+}
+
+// The first topic of every DobLink event is its name
+pub fn event_name(event: &RpcEvent) -> Result<String, Error> {
+    let topic = event
+        .topic
+        .first()
+        .ok_or_else(|| Error::Decode { event_id: event.id.clone(), name: String::new() })?;
+    match ScVal::from_xdr_base64(topic, Limits::none())? {
+        ScVal::Symbol(symbol) => Ok(symbol.0.to_utf8_string_lossy()),
+        _ => Err(Error::Decode { event_id: event.id.clone(), name: String::new() }),
+    }
+}
+
+// Read schema_version straight from the XDR map so newer payloads fail cleanly instead of misdecoding
+fn schema_version(value: &ScVal) -> Option<u32> {
+    let ScVal::Map(Some(map)) = value else {
+        return None;
+    };
+    map.iter().find_map(|entry| match (&entry.key, &entry.val) {
+        (ScVal::Symbol(key), ScVal::U32(version)) if key.0.as_slice() == b"schema_version" => Some(*version),
+        _ => None,
+    })
+}
+
+fn payload<T: TryFromVal<Env, ScVal>>(env: &Env, event: &RpcEvent, name: &str, value: &ScVal) -> Result<T, Error> {
+    T::try_from_val(env, value).map_err(|_| Error::Decode { event_id: event.id.clone(), name: name.into() })
+}
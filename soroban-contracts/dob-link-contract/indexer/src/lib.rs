@@ -0,0 +1,333 @@
+// Off-chain indexer for DobLink contract events.
+// Applies Soroban RPC `getEvents` responses to a SQLite database and exposes per-token,
+// per-buyer and per-widget aggregates built from what actually happened on-chain.
+use std::fmt;
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use soroban_sdk::{Address, Env};
+
+mod decode;
+mod rpc;
+
+pub use decode::{decode_event, event_name, DobLinkEvent};
+pub use rpc::{GetEventsResponse, GetEventsResult, RpcError, RpcEvent};
+
+const SCHEMA: &str = include_str!("schema.sql");
+
+#[derive(Debug)]
+pub enum Error {
+    Json(serde_json::Error),
+    Rpc(RpcError), // The response carried a JSON-RPC error instead of events
+    Xdr(soroban_sdk::xdr::Error),
+    Sqlite(rusqlite::Error),
+    Decode { event_id: String, name: String },
+    UnsupportedSchema { event_id: String, version: Option<u32> },
+    AmountOverflow { event_id: String }, // An amount does not fit in a SQLite integer
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Json(err) => write!(f, "invalid getEvents JSON: {err}"),
+            Error::Rpc(err) => write!(f, "RPC error {}: {}", err.code, err.message),
+            Error::Xdr(err) => write!(f, "invalid event XDR: {err}"),
+            Error::Sqlite(err) => write!(f, "database error: {err}"),
+            Error::Decode { event_id, name } => write!(f, "event {event_id} ({name}) does not match the contract types"),
+            Error::UnsupportedSchema { event_id, version } => {
+                write!(f, "event {event_id} has unsupported schema version {version:?}")
+            }
+            Error::AmountOverflow { event_id } => write!(f, "event {event_id} has an amount too large to store"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+impl From<soroban_sdk::xdr::Error> for Error {
+    fn from(err: soroban_sdk::xdr::Error) -> Self {
+        Error::Xdr(err)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Error::Sqlite(err)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenStats {
+    pub token_id: String,
+    pub investments: i64,
+    pub invested: i64,
+    pub completed: i64,
+    pub raised: i64, // Amount of completed investments
+    pub pending: i64,
+    pub refunded: i64,
+    pub proceeds: i64, // Sent to the issuer treasury after fees
+    pub referral_fees: i64,
+    pub platform_fees: i64,
+    pub holders: i64, // Buyers with at least one completed investment
+    pub yield_deposited: i64,
+    pub yield_claimed: i64,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BuyerStats {
+    pub buyer: String,
+    pub token_id: String,
+    pub investments: i64,
+    pub invested: i64,
+    pub completed: i64,
+    pub pending: i64,
+    pub refunded: i64,
+    pub yield_claimed: i64,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WidgetStats {
+    pub widget_hash: String,
+    pub token_id: String,
+    pub investments: i64,
+    pub invested: i64,
+    pub sales: i64, // Completed investments
+    pub sales_amount: i64,
+    pub referral_fees: i64,
+}
+
+pub struct Indexer {
+    env: Env, // Host environment used only to decode payloads into the contract types
+    conn: Connection,
+    contract_id: String,
+}
+
+impl Indexer {
+    // Open (or create) the database at path, indexing events from the given contract only
+    pub fn open(path: impl AsRef<Path>, contract_id: &str) -> Result<Self, Error> {
+        Self::with_connection(Connection::open(path)?, contract_id)
+    }
+
+    // Index into a throwaway in-memory database
+    pub fn open_in_memory(contract_id: &str) -> Result<Self, Error> {
+        Self::with_connection(Connection::open_in_memory()?, contract_id)
+    }
+
+    fn with_connection(conn: Connection, contract_id: &str) -> Result<Self, Error> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { env: Env::default(), conn, contract_id: contract_id.into() })
+    }
+
+    // Direct access to the database for ad-hoc queries
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    // Apply one getEvents response and return how many new events it contained.
+    // The whole page is applied atomically, and events already indexed are skipped.
+    pub fn ingest(&mut self, json: &str) -> Result<usize, Error> {
+        let response: GetEventsResponse = serde_json::from_str(json)?;
+        if let Some(err) = response.error {
+            return Err(Error::Rpc(err));
+        }
+        let events = response.result.map(|result| result.events).unwrap_or_default();
+
+        let tx = self.conn.transaction()?;
+        let mut applied = 0;
+        for event in &events {
+            if event.kind != "contract" || event.contract_id != self.contract_id || !event.in_successful_contract_call {
+                continue;
+            }
+            let decoded = decode_event(&self.env, event)?;
+            let name = event_name(event)?;
+
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO events (id, ledger, ledger_closed_at, tx_hash, name) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![event.id, event.ledger, event.ledger_closed_at, event.tx_hash, name],
+            )?;
+            if inserted == 0 {
+                continue;
+            }
+            Self::apply(&tx, event, decoded)?;
+            applied += 1;
+        }
+        tx.commit()?;
+        Ok(applied)
+    }
+
+    // The id of the last event applied, to pass as the cursor of the next getEvents request
+    pub fn cursor(&self) -> Result<Option<String>, Error> {
+        Ok(self.conn.query_row("SELECT MAX(id) FROM events", [], |row| row.get(0))?)
+    }
+
+    // Get the aggregates for one offering
+    pub fn token_stats(&self, token_id: &str) -> Result<Option<TokenStats>, Error> {
+        let stats = self
+            .conn
+            .query_row(
+                "SELECT token_id, investments, invested, completed, raised, pending, refunded, proceeds,
+                        referral_fees, platform_fees, holders, yield_deposited, yield_claimed
+                 FROM token_stats WHERE token_id = ?1",
+                params![token_id],
+                |row| {
+                    Ok(TokenStats {
+                        token_id: row.get(0)?,
+                        investments: row.get(1)?,
+                        invested: row.get(2)?,
+                        completed: row.get(3)?,
+                        raised: row.get(4)?,
+                        pending: row.get(5)?,
+                        refunded: row.get(6)?,
+                        proceeds: row.get(7)?,
+                        referral_fees: row.get(8)?,
+                        platform_fees: row.get(9)?,
+                        holders: row.get(10)?,
+                        yield_deposited: row.get(11)?,
+                        yield_claimed: row.get(12)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(stats)
+    }
+
+    // Get the aggregates for one buyer in one offering
+    pub fn buyer_stats(&self, buyer: &str, token_id: &str) -> Result<Option<BuyerStats>, Error> {
+        let stats = self
+            .conn
+            .query_row(
+                "SELECT buyer, token_id, investments, invested, completed, pending, refunded, yield_claimed
+                 FROM buyer_stats WHERE buyer = ?1 AND token_id = ?2",
+                params![buyer, token_id],
+                |row| {
+                    Ok(BuyerStats {
+                        buyer: row.get(0)?,
+                        token_id: row.get(1)?,
+                        investments: row.get(2)?,
+                        invested: row.get(3)?,
+                        completed: row.get(4)?,
+                        pending: row.get(5)?,
+                        refunded: row.get(6)?,
+                        yield_claimed: row.get(7)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(stats)
+    }
+
+    // Get the sales attributed on-chain to one widget
+    pub fn widget_stats(&self, widget_hash: &str) -> Result<Option<WidgetStats>, Error> {
+        let stats = self
+            .conn
+            .query_row(
+                "SELECT widget_hash, token_id, investments, invested, sales, sales_amount, referral_fees
+                 FROM widget_stats WHERE widget_hash = ?1",
+                params![widget_hash],
+                |row| {
+                    Ok(WidgetStats {
+                        widget_hash: row.get(0)?,
+                        token_id: row.get(1)?,
+                        investments: row.get(2)?,
+                        invested: row.get(3)?,
+                        sales: row.get(4)?,
+                        sales_amount: row.get(5)?,
+                        referral_fees: row.get(6)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(stats)
+    }
+
+    // Helper function to write one decoded event into the tables behind the stats views
+    fn apply(tx: &Transaction, event: &RpcEvent, decoded: DobLinkEvent) -> Result<(), Error> {
+        let amount = |value: i128| i64::try_from(value).map_err(|_| Error::AmountOverflow { event_id: event.id.clone() });
+
+        match decoded {
+            DobLinkEvent::InvestmentCreated(created) => {
+                tx.execute(
+                    "INSERT OR REPLACE INTO investments (investment_id, token_id, buyer, widget_hash, amount, status, created_ledger)
+                     VALUES (?1, ?2, ?3, ?4, ?5, 'Pending', ?6)",
+                    params![
+                        created.investment_id,
+                        created.token_id.to_string(),
+                        address(&created.buyer),
+                        created.widget_hash.map(|hash| hash.to_string()),
+                        amount(created.amount)?,
+                        event.ledger,
+                    ],
+                )?;
+            }
+            DobLinkEvent::StatusChanged(changed) => {
+                // Investments created before indexing started are unknown and left out
+                let settlement = changed.settlement;
+                tx.execute(
+                    "UPDATE investments SET status = ?1, refunded = ?2, proceeds = ?3, referral_fee = ?4, platform_fee = ?5
+                     WHERE investment_id = ?6",
+                    params![
+                        format!("{:?}", changed.new_status),
+                        amount(settlement.refunded)?,
+                        amount(settlement.proceeds)?,
+                        amount(settlement.referral_fee)?,
+                        amount(settlement.platform_fee)?,
+                        changed.investment_id,
+                    ],
+                )?;
+            }
+            DobLinkEvent::TokenRegistered(registered) => {
+                tx.execute(
+                    "INSERT OR REPLACE INTO tokens (token_id, name, share_token, delisted) VALUES (?1, ?2, ?3, 0)",
+                    params![registered.token_id.to_string(), registered.name.to_string(), address(&registered.share_token)],
+                )?;
+            }
+            DobLinkEvent::TokenDelisted(delisted) => {
+                tx.execute("UPDATE tokens SET delisted = 1 WHERE token_id = ?1", params![delisted.token_id.to_string()])?;
+            }
+            DobLinkEvent::WidgetRegistered(registered) => {
+                tx.execute(
+                    "INSERT OR REPLACE INTO widgets (widget_hash, token_id, owner, payout, active) VALUES (?1, ?2, ?3, ?4, 1)",
+                    params![
+                        registered.widget_hash.to_string(),
+                        registered.token_id.to_string(),
+                        address(&registered.owner),
+                        address(&registered.payout),
+                    ],
+                )?;
+            }
+            DobLinkEvent::WidgetDeactivated(deactivated) => {
+                tx.execute(
+                    "UPDATE widgets SET active = 0 WHERE widget_hash = ?1",
+                    params![deactivated.widget_hash.to_string()],
+                )?;
+            }
+            DobLinkEvent::YieldDeposited(deposited) => {
+                tx.execute(
+                    "INSERT INTO yield_deposits (event_id, token_id, amount) VALUES (?1, ?2, ?3)",
+                    params![event.id, deposited.token_id.to_string(), amount(deposited.amount)?],
+                )?;
+            }
+            DobLinkEvent::YieldClaimed(claimed) => {
+                tx.execute(
+                    "INSERT INTO yield_claims (event_id, token_id, buyer, amount) VALUES (?1, ?2, ?3, ?4)",
+                    params![event.id, claimed.token_id.to_string(), address(&claimed.buyer), amount(claimed.amount)?],
+                )?;
+            }
+            DobLinkEvent::Other(_) => {}
+        }
+        Ok(())
+    }
+}
+
+// Helper function to render an address as its strkey
+fn address(address: &Address) -> String {
+    address.to_string().to_string()
+}
+
+mod test;
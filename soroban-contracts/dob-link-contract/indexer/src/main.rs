@@ -0,0 +1,33 @@
+use std::process::ExitCode;
+
+use dob_link_indexer::Indexer;
+
+// Apply recorded getEvents responses to a SQLite database:
+//   dob-link-indexer <database> <contract-id> <events.json>...
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 3 {
+        eprintln!("usage: dob-link-indexer <database> <contract-id> <events.json>...");
+        return ExitCode::from(2);
+    }
+
+    match run(&args[0], &args[1], &args[2..]) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(database: &str, contract_id: &str, files: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut indexer = Indexer::open(database, contract_id)?;
+    for file in files {
+        let applied = indexer.ingest(&std::fs::read_to_string(file)?)?;
+        println!("{file}: {applied} new events");
+    }
+    if let Some(cursor) = indexer.cursor()? {
+        println!("cursor: {cursor}");
+    }
+    Ok(())
+}
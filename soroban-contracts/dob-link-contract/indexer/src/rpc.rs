@@ -0,0 +1,46 @@
+use serde::Deserialize;
+
+// A Soroban RPC `getEvents` JSON-RPC response
+#[derive(Clone, Debug, Deserialize)]
+pub struct GetEventsResponse {
+    pub result: Option<GetEventsResult>,
+    pub error: Option<RpcError>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetEventsResult {
+    pub events: Vec<RpcEvent>,
+    pub latest_ledger: u32,
+    #[serde(default)]
+    pub cursor: Option<String>, // Only returned by newer RPC versions
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+// One event as returned by the RPC, with topics and value as base64 XDR ScVals
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcEvent {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub ledger: u32,
+    pub ledger_closed_at: String,
+    pub contract_id: String,
+    pub id: String, // Zero-padded, so ids sort in ledger order
+    pub topic: Vec<String>,
+    pub value: String,
+    #[serde(default = "default_successful")]
+    pub in_successful_contract_call: bool,
+    #[serde(default)]
+    pub tx_hash: String,
+}
+
+// Older RPC versions only return events from successful calls and omit the flag
+fn default_successful() -> bool {
+    true
+}
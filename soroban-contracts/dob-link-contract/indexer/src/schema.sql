@@ -0,0 +1,99 @@
+-- Every DobLink event applied so far, keyed by its RPC event id so re-ingesting a page is a no-op
+CREATE TABLE IF NOT EXISTS events (
+    id TEXT PRIMARY KEY,
+    ledger INTEGER NOT NULL,
+    ledger_closed_at TEXT NOT NULL,
+    tx_hash TEXT NOT NULL,
+    name TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tokens (
+    token_id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    share_token TEXT NOT NULL,
+    delisted INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS widgets (
+    widget_hash TEXT PRIMARY KEY,
+    token_id TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    payout TEXT NOT NULL,
+    active INTEGER NOT NULL DEFAULT 1
+);
+
+-- One row per investment, updated in place as its status changes
+CREATE TABLE IF NOT EXISTS investments (
+    investment_id INTEGER PRIMARY KEY,
+    token_id TEXT NOT NULL,
+    buyer TEXT NOT NULL,
+    widget_hash TEXT,
+    amount INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    created_ledger INTEGER NOT NULL,
+    refunded INTEGER NOT NULL DEFAULT 0,
+    proceeds INTEGER NOT NULL DEFAULT 0,
+    referral_fee INTEGER NOT NULL DEFAULT 0,
+    platform_fee INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS yield_deposits (
+    event_id TEXT PRIMARY KEY,
+    token_id TEXT NOT NULL,
+    amount INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS yield_claims (
+    event_id TEXT PRIMARY KEY,
+    token_id TEXT NOT NULL,
+    buyer TEXT NOT NULL,
+    amount INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS investments_by_buyer ON investments (buyer, token_id);
+CREATE INDEX IF NOT EXISTS investments_by_widget ON investments (widget_hash);
+
+CREATE VIEW IF NOT EXISTS token_stats AS
+SELECT
+    i.token_id,
+    COUNT(*) AS investments,
+    SUM(i.amount) AS invested,
+    SUM(i.status = 'Completed') AS completed,
+    SUM(CASE WHEN i.status = 'Completed' THEN i.amount ELSE 0 END) AS raised,
+    SUM(CASE WHEN i.status = 'Pending' THEN i.amount ELSE 0 END) AS pending,
+    SUM(i.refunded) AS refunded,
+    SUM(i.proceeds) AS proceeds,
+    SUM(i.referral_fee) AS referral_fees,
+    SUM(i.platform_fee) AS platform_fees,
+    COUNT(DISTINCT CASE WHEN i.status = 'Completed' THEN i.buyer END) AS holders,
+    (SELECT COALESCE(SUM(d.amount), 0) FROM yield_deposits d WHERE d.token_id = i.token_id) AS yield_deposited,
+    (SELECT COALESCE(SUM(c.amount), 0) FROM yield_claims c WHERE c.token_id = i.token_id) AS yield_claimed
+FROM investments i
+GROUP BY i.token_id;
+
+CREATE VIEW IF NOT EXISTS buyer_stats AS
+SELECT
+    i.buyer,
+    i.token_id,
+    COUNT(*) AS investments,
+    SUM(i.amount) AS invested,
+    SUM(CASE WHEN i.status = 'Completed' THEN i.amount ELSE 0 END) AS completed,
+    SUM(CASE WHEN i.status = 'Pending' THEN i.amount ELSE 0 END) AS pending,
+    SUM(i.refunded) AS refunded,
+    (SELECT COALESCE(SUM(c.amount), 0) FROM yield_claims c WHERE c.token_id = i.token_id AND c.buyer = i.buyer) AS yield_claimed
+FROM investments i
+GROUP BY i.buyer, i.token_id;
+
+-- Sales attributed on-chain to each widget, as opposed to the widget's self-reported analytics
+CREATE VIEW IF NOT EXISTS widget_stats AS
+SELECT
+    i.widget_hash,
+    i.token_id,
+    COUNT(*) AS investments,
+    SUM(i.amount) AS invested,
+    SUM(i.status = 'Completed') AS sales,
+    SUM(CASE WHEN i.status = 'Completed' THEN i.amount ELSE 0 END) AS sales_amount,
+    SUM(i.referral_fee) AS referral_fees
+FROM investments i
+WHERE i.widget_hash IS NOT NULL
+GROUP BY i.widget_hash, i.token_id;